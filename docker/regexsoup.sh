@@ -1,2 +1,3 @@
 #!/bin/sh
+# `exec` so that SIGTERM from runit reaches regexsoup, which closes the games and the gateway
 exec /usr/local/bin/regexsoup
@@ -0,0 +1,255 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! `regexsoup-cli`: a terminal frontend for solo practice
+//!
+//! ```text
+//! $ regexsoup-cli --seed 42
+//! > start 4
+//! > query abba
+//! > guess (a|b)*
+//! > summary
+//! > giveup
+//! ```
+
+#![feature(format_args_capture)]
+
+use anyhow::{anyhow, bail, Context};
+use regexsoup::{
+    engine::{GameMode, Player, PlayerId, Quiz, Rules},
+    regex::{randomly_generate, randomly_generate_from_seed, Difficulty},
+};
+use std::{
+    convert::TryInto,
+    io::{self, BufRead, Write},
+    num::NonZeroU8,
+};
+
+const HELP: &str = "\
+commands:
+  start [SIZE] [MODE]  starts a fresh game (SIZE: 1-10, MODE: normal|golf)
+  query INPUT          tests whether INPUT matches the answer (`\"\"` for the empty string)
+  guess REGEX          checks your answer
+  summary              shows the history of queries
+  giveup               reveals the answer
+  help                 shows this message
+  quit                 exits";
+
+const USAGE: &str = "usage: regexsoup-cli [--seed SEED]";
+
+/// A local game loop of a single player
+struct Repl {
+    player: Player,
+    /// seed of the next game
+    seed: Option<u64>,
+    quiz: Option<Quiz>,
+}
+
+impl Repl {
+    fn new(seed: Option<u64>) -> Self {
+        let name = std::env::var("USER").unwrap_or_else(|_| "you".to_string());
+        Repl {
+            player: Player {
+                id: PlayerId(0),
+                name,
+            },
+            seed,
+            quiz: None,
+        }
+    }
+
+    fn quiz(&mut self) -> anyhow::Result<&mut Quiz> {
+        self.quiz
+            .as_mut()
+            .ok_or_else(|| anyhow!("no game is running. `start` a fresh game first."))
+    }
+
+    fn start(&mut self, args: &[&str]) -> anyhow::Result<String> {
+        let size: NonZeroU8 = match args.first() {
+            Some(size) => size
+                .parse::<u8>()
+                .ok()
+                .filter(|size| (1..=10).contains(size))
+                .ok_or_else(|| anyhow!("SIZE must be 1-10: {size}"))?
+                .try_into()?,
+            None => 3u8.try_into()?,
+        };
+        let mode: GameMode = match args.get(1) {
+            Some(mode) => mode.parse()?,
+            None => GameMode::Normal,
+        };
+
+        let difficulty = Difficulty(size);
+        let regex = match self.seed {
+            Some(seed) => {
+                // games in a session are different but reproducible
+                self.seed = Some(seed.wrapping_add(1));
+                randomly_generate_from_seed(&difficulty, seed)
+            }
+            None => randomly_generate(&difficulty),
+        };
+        let rules = Rules {
+            mode,
+            ..Rules::default()
+        };
+        let mut quiz = Quiz::with_answer(regex, size, rules);
+        quiz.register(self.player.id)?;
+        let introduction = quiz.introduction().to_string();
+        let replaced = self.quiz.replace(quiz).is_some();
+        Ok(if replaced {
+            format!("{introduction}(the old game is expired.)\n")
+        } else {
+            introduction
+        })
+    }
+
+    fn execute(&mut self, line: &str) -> anyhow::Result<Option<String>> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(Some(String::new()));
+        }
+        // the argument is the rest of the line, which may contain spaces
+        let (command, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(command, rest)| (command, rest.trim()));
+        let argument = || {
+            Some(rest)
+                .filter(|rest| !rest.is_empty())
+                .ok_or_else(|| anyhow!("`{command}` needs an argument"))
+        };
+
+        let output = match command {
+            "start" => self.start(&rest.split_whitespace().collect::<Vec<_>>())?,
+            "query" => {
+                let input = argument()?;
+                let id = self.player.id;
+                self.quiz()?.query(id, input)?.to_string()
+            }
+            "guess" => {
+                let input = argument()?;
+                let player = self.player.clone();
+                let quiz = self.quiz()?;
+                let output = quiz.inspect(&player, input)?.to_string();
+                if quiz.is_solved() {
+                    self.quiz = None;
+                }
+                output
+            }
+            "summary" => {
+                let id = self.player.id;
+                self.quiz()?.get_query_history(id).to_string()
+            }
+            "giveup" | "give-up" => {
+                let player = self.player.clone();
+                let quiz = self.quiz()?;
+                // the only player leaves, so the answer is revealed without the feedback
+                // buttons, which work only on Discord
+                quiz.accepts_give_up(&player)?;
+                let output = quiz.revelation();
+                self.quiz = None;
+                output
+            }
+            "help" => format!("{HELP}\n"),
+            "quit" | "exit" => return Ok(None),
+            _ => bail!("unknown command: {command} (see `help`)"),
+        };
+        Ok(Some(output))
+    }
+}
+
+fn parse_args() -> anyhow::Result<Option<u64>> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match &args[..] {
+        [] => Ok(None),
+        [flag, seed] if flag == "--seed" => {
+            Ok(Some(seed.parse().with_context(|| {
+                anyhow!("SEED must be an integer: {seed}")
+            })?))
+        }
+        _ => bail!("{USAGE}"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut repl = Repl::new(parse_args()?);
+    println!("REGEX-SOUP (type `help` to see commands)");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match repl.execute(&line) {
+            Ok(Some(output)) => print!("{output}"),
+            Ok(None) => break,
+            Err(why) => println!("error: {why}"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Repl;
+    use regexsoup::regex::{randomly_generate_from_seed, Difficulty};
+    use std::convert::TryInto;
+
+    #[test]
+    fn play_a_seeded_game() {
+        let mut repl = Repl::new(Some(42));
+        let answer = randomly_generate_from_seed(&Difficulty(2u8.try_into().unwrap()), 42);
+
+        let introduction = repl.execute("start 2").unwrap().unwrap();
+        assert!(introduction.starts_with("# "));
+        assert!(repl
+            .execute("query ab")
+            .unwrap()
+            .unwrap()
+            .starts_with("ab => "));
+        assert!(repl.execute("query xyz").is_err());
+        assert!(repl.execute("summary").unwrap().unwrap().contains("ab: "));
+
+        // the whole line is the guess, not only its first word
+        assert!(repl
+            .execute("guess a | b")
+            .unwrap_err()
+            .to_string()
+            .contains(" | b"));
+        let guess = answer.to_string().replace(' ', "");
+        assert_eq!(
+            repl.execute(&format!("guess {guess}")).unwrap(),
+            Some(format!("{guess} => AC\n"))
+        );
+        // the solved game is closed
+        assert!(repl.execute("query ab").is_err());
+
+        // the next game follows the seed
+        repl.execute("start 2").unwrap();
+        let answer = randomly_generate_from_seed(&Difficulty(2u8.try_into().unwrap()), 43);
+        let revealed = repl.execute("giveup").unwrap().unwrap();
+        assert!(revealed.contains(&format!("The answer is `{}`", answer.simplify())));
+        assert!(!revealed.contains("[Good]"));
+        assert!(repl.execute("giveup").is_err());
+        assert_eq!(repl.execute("quit").unwrap(), None);
+    }
+}
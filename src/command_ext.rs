@@ -17,157 +17,262 @@
  *
  */
 
-use crate::concepts::Satisfied;
+use crate::response::{Embed, Render, Response};
 use anyhow::{anyhow, Context};
 use serenity::{
     async_trait,
-    builder::{CreateButton, CreateEmbed},
-    http::Http,
+    http::{AttachmentType, Http},
     model::interactions::{
         application_command::ApplicationCommandInteraction,
-        message_component::MessageComponentInteraction, InteractionResponseType,
+        message_component::MessageComponentInteraction,
+        InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
     },
 };
+use std::borrow::Cow;
 
-/// workaround
-pub struct Button<const N: usize> {}
-impl Satisfied for Button<1> {}
-impl Satisfied for Button<2> {}
-impl Satisfied for Button<3> {}
-impl Satisfied for Button<4> {}
-impl Satisfied for Button<5> {}
+/// A file attached to a message
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    fn as_attachment_type(&self) -> AttachmentType<'_> {
+        AttachmentType::Bytes {
+            data: Cow::from(&self.data[..]),
+            filename: self.filename.clone(),
+        }
+    }
+}
 
 /// Common interface of Command and Component
 #[async_trait]
 pub trait CommandExt {
+    /// Responds to the interaction with a [Response] rendered by [Render].
+    async fn respond(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
+    ) -> anyhow::Result<()>;
     async fn message<T: ToString + Send + Sync>(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
         content: T,
+    ) -> anyhow::Result<()> {
+        self.respond(http, Response::message(content)).await
+    }
+    /// Responds with a [Response] only visible to the user who invoked the interaction.
+    async fn respond_ephemeral(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
     ) -> anyhow::Result<()>;
+    /// Sends a message only visible to the user who invoked the interaction.
+    async fn ephemeral<T: ToString + Send + Sync>(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        content: T,
+    ) -> anyhow::Result<()> {
+        self.respond_ephemeral(http, Response::message(content))
+            .await
+    }
     async fn embed(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
-        embed: CreateEmbed,
+        embed: Embed,
+    ) -> anyhow::Result<()> {
+        self.respond(http, embed.into()).await
+    }
+    /// Acknowledges the interaction to respond later with [edit](Self::edit).
+    /// Discord requires the first response within 3 seconds.
+    async fn defer(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+    ) -> anyhow::Result<()>;
+    /// Edits the original response, such as a deferred one.
+    async fn edit(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
+    ) -> anyhow::Result<()>;
+    /// Sends a follow-up message.
+    /// The interaction must have been responded beforehand.
+    async fn follow_up(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
     ) -> anyhow::Result<()>;
-    async fn button<const N: usize>(
+    /// Sends a follow-up message with a file.
+    /// The interaction must have been responded beforehand.
+    async fn attachment(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
         msg: impl ToString + Send + Sync + 'async_trait,
-        buttons: [CreateButton; N],
-    ) -> anyhow::Result<()>
-    where
-        Button<N>: Satisfied;
+        attachment: Attachment,
+    ) -> anyhow::Result<()>;
 }
 
 #[async_trait]
 impl CommandExt for ApplicationCommandInteraction {
-    async fn message<T: ToString + Send + Sync>(
+    async fn respond(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
-        content: T,
+        response: Response,
     ) -> anyhow::Result<()> {
-        self.create_interaction_response(&http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| message.content(content))
+        self.create_interaction_response(&http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| response.render(data))
         })
         .await
         .with_context(|| anyhow!("serenity error"))
     }
 
-    async fn embed(
+    async fn respond_ephemeral(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
-        embed: CreateEmbed,
+        response: Response,
     ) -> anyhow::Result<()> {
-        self.create_interaction_response(&http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| message.add_embed(embed))
+        self.create_interaction_response(&http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| {
+                    response
+                        .render(data)
+                        .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                })
         })
         .await
         .with_context(|| anyhow!("serenity error"))
     }
 
-    async fn button<const N: usize>(
+    async fn defer(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+    ) -> anyhow::Result<()> {
+        self.create_interaction_response(&http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await
+        .with_context(|| anyhow!("serenity error"))
+    }
+
+    async fn edit(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
+    ) -> anyhow::Result<()> {
+        self.edit_original_interaction_response(&http, |r| response.render(r))
+            .await
+            .map(|_| ())
+            .with_context(|| anyhow!("serenity error"))
+    }
+
+    async fn follow_up(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
+    ) -> anyhow::Result<()> {
+        self.create_followup_message(&http, |message| response.render(message))
+            .await
+            .map(|_| ())
+            .with_context(|| anyhow!("serenity error"))
+    }
+
+    async fn attachment(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
         msg: impl ToString + Send + Sync + 'async_trait,
-        buttons: [CreateButton; N],
-    ) -> anyhow::Result<()>
-    where
-        Button<N>: Satisfied,
-    {
-        self.create_interaction_response(&http, |response| {
-            response.interaction_response_data(|message| {
-                message.content(msg).components(|component| {
-                    component.create_action_row(|action_row| {
-                        for button in buttons {
-                            action_row.add_button(button);
-                        }
-                        action_row
-                    })
-                })
-            })
+        attachment: Attachment,
+    ) -> anyhow::Result<()> {
+        self.create_followup_message(&http, |message| {
+            message
+                .content(msg)
+                .add_file(attachment.as_attachment_type())
         })
         .await
+        .map(|_| ())
         .with_context(|| anyhow!("serenity error"))
     }
 }
 
 #[async_trait]
 impl CommandExt for MessageComponentInteraction {
-    async fn message<T: ToString + Send + Sync>(
+    async fn respond(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
-        content: T,
+        response: Response,
     ) -> anyhow::Result<()> {
-        self.create_interaction_response(&http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| message.content(content))
+        self.create_interaction_response(&http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| response.render(data))
         })
         .await
         .with_context(|| anyhow!("serenity error"))
     }
 
-    async fn embed(
+    async fn respond_ephemeral(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
-        embed: CreateEmbed,
+        response: Response,
     ) -> anyhow::Result<()> {
-        self.create_interaction_response(&http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| message.add_embed(embed))
+        self.create_interaction_response(&http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| {
+                    response
+                        .render(data)
+                        .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                })
         })
         .await
         .with_context(|| anyhow!("serenity error"))
     }
 
-    async fn button<const N: usize>(
+    async fn defer(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+    ) -> anyhow::Result<()> {
+        self.create_interaction_response(&http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await
+        .with_context(|| anyhow!("serenity error"))
+    }
+
+    async fn edit(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
+    ) -> anyhow::Result<()> {
+        self.edit_original_interaction_response(&http, |r| response.render(r))
+            .await
+            .map(|_| ())
+            .with_context(|| anyhow!("serenity error"))
+    }
+
+    async fn follow_up(
+        &self,
+        http: impl AsRef<Http> + Send + Sync + 'async_trait,
+        response: Response,
+    ) -> anyhow::Result<()> {
+        self.create_followup_message(&http, |message| response.render(message))
+            .await
+            .map(|_| ())
+            .with_context(|| anyhow!("serenity error"))
+    }
+
+    async fn attachment(
         &self,
         http: impl AsRef<Http> + Send + Sync + 'async_trait,
         msg: impl ToString + Send + Sync + 'async_trait,
-        buttons: [CreateButton; N],
-    ) -> anyhow::Result<()>
-    where
-        Button<N>: Satisfied,
-    {
-        self.create_interaction_response(&http, |response| {
-            response.interaction_response_data(|message| {
-                message.content(msg).components(|component| {
-                    component.create_action_row(|action_row| {
-                        for button in buttons {
-                            action_row.add_button(button);
-                        }
-                        action_row
-                    })
-                })
-            })
+        attachment: Attachment,
+    ) -> anyhow::Result<()> {
+        self.create_followup_message(&http, |message| {
+            message
+                .content(msg)
+                .add_file(attachment.as_attachment_type())
         })
         .await
+        .map(|_| ())
         .with_context(|| anyhow!("serenity error"))
     }
 }
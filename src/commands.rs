@@ -17,166 +17,339 @@
  *
  */
 
-use crate::{bot::Quiz, errors::CommandError};
+use crate::{
+    command_options,
+    engine::{GameMode, PlayerId, Quiz, Rules},
+    errors::CommandError,
+    metrics::METRICS,
+    options::CommandOptions,
+    parser::{CustomId, Menu},
+    regex::{GenerationParams, ProblemScorer, RegexAst},
+    registry::{Dictionary, Registry},
+    response::{Colour, Embed, SelectMenu, SelectMenuOption},
+    settings::GuildSettings,
+};
 use anyhow::{anyhow, Context};
-use serenity::{
-    builder::CreateEmbed,
-    http::Http,
-    model::interactions::application_command::{ApplicationCommand, ApplicationCommandOptionType},
-    utils::Colour,
+use std::{
+    convert::TryInto,
+    num::NonZeroU8,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
-use std::{num::NonZeroU8, time::Duration};
-use tokio::{sync::oneshot, time::timeout};
-
-pub async fn generate_regex(difficulty: NonZeroU8) -> anyhow::Result<Quiz> {
-    let (tx, rx) = oneshot::channel();
+use tokio::time::timeout;
 
-    tokio::task::spawn(async move {
-        let quiz = Quiz::new_with_difficulty(difficulty);
-        let _ = tx.send(quiz);
+/// Generates a quiz within `limit`.
+/// The response has been deferred, so that it can take longer than 3 seconds.
+pub async fn generate_regex(
+    difficulty: NonZeroU8,
+    rules: Rules,
+    params: GenerationParams,
+    scorer: Arc<dyn ProblemScorer + Send + Sync>,
+    limit: Duration,
+) -> anyhow::Result<Quiz> {
+    // the generation is CPU-bound
+    let started = Instant::now();
+    // the blocking thread is not stopped by the timeout, so it gives up by itself
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let generation = tokio::task::spawn_blocking({
+        let cancelled = Arc::clone(&cancelled);
+        move || {
+            Quiz::generate_until(difficulty, rules, &params, scorer.as_ref(), |_| {
+                cancelled.load(Ordering::Relaxed)
+            })
+        }
     });
 
-    // Wrap the future with a `Timeout` set to expire in 1000 ms.
-    match timeout(Duration::from_millis(1000), rx).await {
-        Ok(quiz) => quiz.with_context(|| anyhow!("receive error")),
-        Err(_) => Err(anyhow::Error::from(CommandError::Timeout {
-            limit: "Time Limit Exceeded".to_string(),
-        }))
-        .context("timeout while generating regex"),
+    match timeout(limit, generation).await {
+        Ok(quiz) => {
+            let quiz = quiz
+                .with_context(|| anyhow!("generation error"))?
+                .ok_or_else(|| anyhow!("generation is cancelled"))?;
+            METRICS.generated(&quiz, started.elapsed());
+            Ok(quiz)
+        }
+        Err(_) => {
+            cancelled.store(true, Ordering::Relaxed);
+            METRICS.generation_timed_out(difficulty.get());
+            Err(anyhow::Error::from(CommandError::Timeout {
+                limit: format!("{}s", limit.as_secs()),
+            }))
+            .context("timeout while generating regex")
+        }
     }
 }
 
-pub fn help() -> CreateEmbed {
-    use indoc::indoc;
-    let mut embed = CreateEmbed::default();
-    embed.colour(Colour::DARK_GREEN).title("HELP");
-    embed
-        .field(
-            "REGEX-SOUP 101",
-            indoc! {
-                "`/start` => `/join` => `/query` => (`/summary`) => `/guess`"
-            },
-            false,
+/// Difficulty/mode picker sent after a bare `/start`, listing only `modes`
+pub fn start_menu(modes: &[GameMode]) -> SelectMenu {
+    let options = (1..=10u8)
+        .flat_map(|size| {
+            modes.iter().map(move |mode| SelectMenuOption {
+                description: format!("{size} characters in the domain-set, {mode} mode"),
+                label: format!("size {size} / {mode}"),
+                value: format!("{size}:{mode}"),
+            })
+        })
+        .collect();
+    SelectMenu {
+        custom_id: CustomId::Select {
+            menu: Menu::Start,
+            values: vec![],
+        }
+        .to_string(),
+        content: "Choose the difficulty and the mode of a fresh REGEX-SOUP.".to_string(),
+        placeholder: "difficulty / mode".to_string(),
+        min_value: 1,
+        max_value: 1,
+        options,
+    }
+}
+
+command_options! {
+    pub struct StartOptions {
+        size: Option<NonZeroU8>,
+        mode: Option<GameMode>,
+        #[default = false]
+        turns: bool,
+        #[default = false]
+        private: bool,
+    }
+}
+
+/// What `/start` does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPlan {
+    /// sends the picker of [start_menu]
+    Menu(SelectMenu),
+    /// starts a game right away
+    Game { size: NonZeroU8, rules: Rules },
+}
+
+/// Plans `/start` with the options in `dictionary`, filling the omitted ones by `settings`.
+/// A bare `/start` shows the picker only if the server fixes neither the size nor the mode.
+pub fn plan_start(
+    dictionary: &Dictionary,
+    settings: &GuildSettings,
+    default_size: NonZeroU8,
+) -> Result<StartPlan, CommandError> {
+    if dictionary.is_empty() && settings.default_size.is_none() && settings.allowed_modes.len() > 1
+    {
+        return Ok(StartPlan::Menu(start_menu(&settings.allowed_modes)));
+    }
+    let options = StartOptions::parse(dictionary)?;
+    Ok(StartPlan::Game {
+        size: settings.size(options.size, default_size),
+        rules: settings.rules(options.mode, options.turns, options.private)?,
+    })
+}
+
+/// Parses a value of [start_menu] in the form of `{size}:{mode}`.
+pub fn parse_start_choice(value: &str) -> anyhow::Result<(NonZeroU8, GameMode)> {
+    let (size, mode) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid choice: {value}"))?;
+    let size: NonZeroU8 = size.parse::<u8>()?.try_into()?;
+    Ok((size, mode.parse()?))
+}
+
+/// Maximum number of choices in an autocomplete
+pub const SUGGESTION_LIMIT: usize = 25;
+/// Maximum length of the label and the value of a choice
+const CHOICE_LENGTH_LIMIT: usize = 100;
+
+/// A choice of an autocomplete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub label: String,
+    pub value: String,
+}
+
+impl Suggestion {
+    fn new(label: impl ToString, value: impl ToString) -> Self {
+        Suggestion {
+            label: label
+                .to_string()
+                .chars()
+                .take(CHOICE_LENGTH_LIMIT)
+                .collect(),
+            value: value.to_string(),
+        }
+    }
+
+    fn fits(&self) -> bool {
+        self.value.chars().count() <= CHOICE_LENGTH_LIMIT
+    }
+}
+
+/// Words not asked yet, starting with `input`.
+pub fn query_suggestions(quiz: &Quiz, player: PlayerId, input: &str) -> Vec<Suggestion> {
+    quiz.unasked_words(player, input, SUGGESTION_LIMIT)
+        .into_iter()
+        .map(|word| Suggestion::new(&word, &word))
+        .filter(Suggestion::fits)
+        .collect()
+}
+
+/// Syntax check of `input`, followed by recent guesses.
+pub fn guess_suggestions(quiz: &Quiz, input: &str) -> Vec<Suggestion> {
+    let check = (!input.is_empty()).then(|| match RegexAst::syntax_error_at(input) {
+        Some(column) => Suggestion::new(format!("syntax error at {column}: {input}"), input),
+        None => Suggestion::new(format!("{input} (valid)"), input),
+    });
+    check
+        .into_iter()
+        .chain(
+            quiz.recent_guesses(SUGGESTION_LIMIT)
+                .into_iter()
+                .filter(|guess| guess != input)
+                .map(|guess| Suggestion::new(format!("{guess} (recent)"), guess)),
         )
-        .field(
-            "/start [DIFFICULTY]",
-            indoc! {
-                "[DIFFICULTY]: number of alphabets"
+        .filter(Suggestion::fits)
+        .take(SUGGESTION_LIMIT)
+        .collect()
+}
+
+/// Help of REGEX-SOUP followed by the commands in `registry`
+pub fn help(registry: &Registry) -> Embed {
+    registry.help(Embed::new(Colour::DarkGreen, "HELP").field(
+        "REGEX-SOUP 101",
+        "`/start` => `/join` => `/query` => (`/summary`) => `/guess`",
+        false,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_regex, plan_start, StartPlan};
+    use crate::{
+        engine::{GameMode, Rules},
+        notification::{Notification, SlashCommand},
+        regex::{GenerationParams, ProblemScorer, RegexAst},
+        registry::Dictionary,
+        settings::{AllowedModes, GuildSettings},
+    };
+    use serenity::model::interactions::application_command::ApplicationCommandInteractionDataOptionValue as OptionValue;
+    use std::{
+        convert::TryInto,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    /// Rejects every problem, counting the candidates
+    struct Rejecting(AtomicUsize);
+
+    impl ProblemScorer for Rejecting {
+        fn score(&self, _ast: &RegexAst) -> f64 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            0.0
+        }
+    }
+
+    #[tokio::test]
+    async fn generation_stops_after_the_timeout() {
+        let scorer = Arc::new(Rejecting(AtomicUsize::new(0)));
+        let generation = generate_regex(
+            2u8.try_into().unwrap(),
+            Rules::default(),
+            // every candidate is scored
+            GenerationParams {
+                min_acceptance_rate: -1.0,
+                max_acceptance_rate: 2.0,
+                ..GenerationParams::default()
             },
-            false,
+            Arc::clone(&scorer) as Arc<dyn ProblemScorer + Send + Sync>,
+            Duration::from_millis(50),
         )
-        .field(
-            "/query [INPUT]",
-            indoc! {r#"
-                [INPUT]: alphabets to test (`""` is accepted as empty string)
-            "#},
-            false,
-        )
-        .field(
-            "/guess [INPUT]",
-            indoc! {r#"
-                Check your answer.
-                [INPUT]: regex you guess
-            "#},
-            false,
-        )
-        .field(
-            "/summary",
-            indoc! {r#"
-                Shows the history of querries.
-            "#},
-            false,
-        )
-        .field(
-            "/join",
-            indoc! {r#"
-                You have to `/join` first to take part in the quiz!
-            "#},
-            false,
+        .await;
+        assert!(generation.is_err());
+
+        // the candidate in progress may be finished
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let tried = scorer.0.load(Ordering::Relaxed);
+        assert!(tried > 0);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(scorer.0.load(Ordering::Relaxed), tried);
+    }
+
+    fn option(name: &str, value: OptionValue) -> (String, Notification) {
+        (
+            name.to_string(),
+            Notification::SlashCommand(SlashCommand::Option(Box::new(value))),
         )
-        .field(
-            "/give-up",
-            indoc! {r#"
-                When all participants have `give-up`,
-                the quiz will end and the answers will be revealed!
-            "#},
-            false,
-        );
-    embed
-}
+    }
 
-pub async fn create_slash_commands(
-    http: impl AsRef<Http>,
-) -> anyhow::Result<Vec<ApplicationCommand>> {
-    // start [DIFFICULTY]: ゲームセッション開始コマンド
-    // query: マッチクエリ
-    // guess: 回答試行
-    // summary: 今までのクエリのサマリ表示
-    // join: 参加表明
-    // give-up: 投了
-
-    ApplicationCommand::set_global_application_commands(&http, |commands| {
-        commands
-            .create_application_command(|command| {
-                command
-                    .name("start")
-                    .description("Starting new regex-soup")
-                    .create_option(|o| {
-                        o.name("size")
-                            .description("Please choice number of characters in the domain-set.")
-                            .kind(ApplicationCommandOptionType::Integer)
-                            .add_int_choice(1, 1)
-                            .add_int_choice(2, 2)
-                            .add_int_choice(3, 3)
-                            .add_int_choice(4, 4)
-                            .add_int_choice(5, 5)
-                            .add_int_choice(6, 6)
-                            .add_int_choice(7, 7)
-                            .add_int_choice(8, 8)
-                            .add_int_choice(9, 9)
-                            .add_int_choice(10, 10)
-                            .required(false)
-                    })
-            })
-            .create_application_command(|command| {
-                command
-                    .name("query")
-                    .description("Query whether is matched with regular expression.")
-                    .create_option(|o| {
-                        o.name("input")
-                            .description("Please enter the input you wish to test for a match.")
-                            .kind(ApplicationCommandOptionType::String)
-                            .required(true)
-                    })
-            })
-            .create_application_command(|command| {
-                command
-                    .name("guess")
-                    .description("Check your answer.")
-                    .create_option(|o| {
-                        o.name("regex")
-                            .description("Please enter the regex you guess.")
-                            .kind(ApplicationCommandOptionType::String)
-                            .required(true)
-                    })
-            })
-            .create_application_command(|command| {
-                command
-                    .name("summary")
-                    .description("Dump the results of the query so far.")
-            })
-            .create_application_command(|command| {
-                command
-                    .name("join")
-                    .description("Register your participation.")
-            })
-            .create_application_command(|command| {
-                command
-                    .name("give-up")
-                    .description("Register your despair.")
-            })
-            .create_application_command(|command| command.name("help").description("helpful"))
-    })
-    .await
-    .with_context(|| anyhow!("serenity error"))
+    #[test]
+    fn bare_start_follows_the_server_defaults() {
+        let default = 3u8.try_into().unwrap();
+        let bare = Dictionary::new();
+        assert!(matches!(
+            plan_start(&bare, &GuildSettings::default(), default),
+            Ok(StartPlan::Menu(_))
+        ));
+
+        let sized = GuildSettings {
+            default_size: Some(5u8.try_into().unwrap()),
+            ..GuildSettings::default()
+        };
+        match plan_start(&bare, &sized, default) {
+            Ok(StartPlan::Game { size, rules }) => {
+                assert_eq!(size.get(), 5);
+                assert_eq!(rules.mode, GameMode::Normal);
+            }
+            plan => panic!("{:?}", plan),
+        }
+
+        let golf_only = GuildSettings {
+            allowed_modes: "golf".parse::<AllowedModes>().unwrap().0,
+            query_budget: Some(10),
+            ..GuildSettings::default()
+        };
+        match plan_start(&bare, &golf_only, default) {
+            Ok(StartPlan::Game { size, rules }) => {
+                assert_eq!(size, default);
+                assert_eq!(rules.mode, GameMode::Golf);
+                assert_eq!(rules.query_budget, Some(10));
+            }
+            plan => panic!("{:?}", plan),
+        }
+    }
+
+    #[test]
+    fn options_of_start_override_the_server_defaults() {
+        let default = 3u8.try_into().unwrap();
+        let settings = GuildSettings {
+            default_size: Some(5u8.try_into().unwrap()),
+            ..GuildSettings::default()
+        };
+        let dictionary = vec![
+            option("size", OptionValue::Integer(2)),
+            option("mode", OptionValue::String("golf".into())),
+            option("turns", OptionValue::Boolean(true)),
+        ]
+        .into_iter()
+        .collect();
+        match plan_start(&dictionary, &settings, default) {
+            Ok(StartPlan::Game { size, rules }) => {
+                assert_eq!(size.get(), 2);
+                assert_eq!(rules.mode, GameMode::Golf);
+                assert!(rules.turn_based);
+                assert!(!rules.private_queries);
+            }
+            plan => panic!("{:?}", plan),
+        }
+
+        let golf_only = GuildSettings {
+            allowed_modes: vec![GameMode::Golf],
+            ..GuildSettings::default()
+        };
+        let normal = vec![option("mode", OptionValue::String("normal".into()))]
+            .into_iter()
+            .collect();
+        assert!(plan_start(&normal, &golf_only, default).is_err());
+    }
 }
@@ -0,0 +1,571 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Configuration of the BOT and the HTTP API, read from `regexsoup.toml`
+//!
+//! Every key is optional and defaults to the value in `regexsoup.example.toml`.
+//! The path is `regexsoup.toml` in the working directory, or `REGEX_SOUP_CONFIG` if set.
+//! Some keys are overridden by the environment:
+//!
+//! | key                      | environment               |
+//! |--------------------------|---------------------------|
+//! | `discord.token`          | `REGEX_SOUP_TOKEN`        |
+//! | `discord.application_id` | `REGEX_SOUP_ID`           |
+//! | `timeouts.idle_secs`     | `REGEX_SOUP_IDLE_TIMEOUT` |
+//! | `storage.feedback`       | `REGEX_SOUP_FEEDBACK`     |
+//! | `logging.level`          | `REGEX_SOUP_LOG`          |
+//! | `logging.format`         | `REGEX_SOUP_LOG_FORMAT`   |
+//! | `status.address`         | `REGEX_SOUP_STATUS_ADDR`  |
+//!
+//! `[status]` and `REGEX_SOUP_STATUS_ADDR` were `[metrics]` and `REGEX_SOUP_METRICS_ADDR`,
+//! which are still accepted.
+
+use crate::{
+    errors::ConfigError,
+    regex::{is_reachable, Difficulty, GenerationParams},
+};
+use serde::Deserialize;
+use std::{
+    convert::TryInto,
+    fs,
+    io::ErrorKind,
+    net::SocketAddr,
+    num::NonZeroU8,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use strum_macros::{Display, EnumString};
+
+const DEFAULT_PATH: &str = "regexsoup.toml";
+/// Number of alphabets available in the domain
+const MAX_SIZE: u8 = 10;
+/// Candidates tried for `default_size` before the generation settings are rejected
+const REACHABILITY_ATTEMPTS: usize = 2000;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub discord: DiscordConfig,
+    pub generation: GenerationConfig,
+    pub timeouts: TimeoutConfig,
+    pub storage: StorageConfig,
+    pub logging: LoggingConfig,
+    pub features: FeatureConfig,
+    /// formerly `[metrics]`, which is still accepted
+    #[serde(alias = "metrics")]
+    pub status: StatusConfig,
+}
+
+/// Credentials of the BOT
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub token: Option<String>,
+    /// usually the user id of the BOT
+    pub application_id: Option<u64>,
+    /// users allowed to the commands across the servers, such as `/reset-stats`
+    pub owners: Vec<u64>,
+}
+
+impl DiscordConfig {
+    pub fn is_owner(&self, user: u64) -> bool {
+        self.owners.contains(&user)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationConfig {
+    /// size of games started without `size`
+    pub default_size: u8,
+    pub max_tree_size: u8,
+    pub min_acceptance_rate: f64,
+    pub max_acceptance_rate: f64,
+    pub sample_size: usize,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        let params = GenerationParams::default();
+        GenerationConfig {
+            default_size: 3,
+            max_tree_size: params.max_tree_size,
+            min_acceptance_rate: params.min_acceptance_rate,
+            max_acceptance_rate: params.max_acceptance_rate,
+            sample_size: params.sample_size,
+        }
+    }
+}
+
+impl GenerationConfig {
+    pub fn default_size(&self) -> NonZeroU8 {
+        // validated in `Config::validate`
+        self.default_size.try_into().unwrap()
+    }
+
+    pub fn params(&self) -> GenerationParams {
+        GenerationParams {
+            max_tree_size: self.max_tree_size,
+            min_acceptance_rate: self.min_acceptance_rate,
+            max_acceptance_rate: self.max_acceptance_rate,
+            sample_size: self.sample_size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// time limit of generating a problem
+    pub generation_secs: u64,
+    /// games nobody plays for this time are closed
+    pub idle_secs: u64,
+    /// interval of looking for idle games
+    pub sweep_secs: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            generation_secs: 30,
+            idle_secs: 30 * 60,
+            sweep_secs: 60,
+        }
+    }
+}
+
+impl TimeoutConfig {
+    pub fn generation(&self) -> Duration {
+        Duration::from_secs(self.generation_secs)
+    }
+
+    pub fn idle(&self) -> Duration {
+        Duration::from_secs(self.idle_secs)
+    }
+
+    pub fn sweep(&self) -> Duration {
+        Duration::from_secs(self.sweep_secs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// JSON lines of Good/Bad feedback
+    pub feedback: PathBuf,
+    /// JSON of the settings of each server by `/config`
+    pub guilds: PathBuf,
+    /// JSON lines of the latest finished games, which the feedback buttons refer to
+    pub records: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            feedback: PathBuf::from("feedback.jsonl"),
+            guilds: PathBuf::from("guilds.json"),
+            records: PathBuf::from("records.jsonl"),
+        }
+    }
+}
+
+/// Verbosity of the logs, from the quietest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Output of the logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LogFormat {
+    /// human-readable lines
+    Text,
+    /// a JSON object per line
+    Json,
+}
+
+/// How often the log file is rotated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub level: LogLevel,
+    pub format: LogFormat,
+    /// hides the answers of the games in the logs
+    pub redact_answers: bool,
+    /// directory of the log files, or only the standard output if `None`
+    pub directory: Option<PathBuf>,
+    pub rotation: LogRotation,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: LogLevel::Info,
+            format: LogFormat::Text,
+            redact_answers: true,
+            directory: None,
+            rotation: LogRotation::Daily,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    /// stores Good/Bad votes and weights the generation by them
+    pub feedback: bool,
+    /// suggests inputs of `/query` and `/guess`
+    pub autocomplete: bool,
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        FeatureConfig {
+            feedback: true,
+            autocomplete: true,
+        }
+    }
+}
+
+/// Local HTTP server of the BOT for `/healthz`, `/readyz` and `/metrics`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusConfig {
+    pub enabled: bool,
+    pub address: SocketAddr,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        StatusConfig {
+            enabled: true,
+            address: SocketAddr::from(([127, 0, 0, 1], 9100)),
+        }
+    }
+}
+
+/// Parses the value of `env` if it is set.
+fn parse_env<T: FromStr>(
+    env: &'static str,
+    key: &'static str,
+    value: Option<String>,
+) -> Result<Option<T>, ConfigError> {
+    value
+        .map(|value| {
+            value.parse().map_err(|_| ConfigError::Invalid {
+                key,
+                reason: format!("is overridden by `{env}` with an invalid value {value:?}"),
+            })
+        })
+        .transpose()
+}
+
+impl Config {
+    /// Loads the config from `REGEX_SOUP_CONFIG` or `regexsoup.toml`,
+    /// applies the environment, and validates the result.
+    /// A missing `regexsoup.toml` is the same as an empty one.
+    pub fn load() -> Result<Self, ConfigError> {
+        let (path, required) = match std::env::var("REGEX_SOUP_CONFIG") {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_PATH), false),
+        };
+        let mut config = match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text, &path)?,
+            Err(why) if why.kind() == ErrorKind::NotFound && !required => Config::default(),
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+        config.apply_env(|name| std::env::var(name).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn parse(text: &str, path: &Path) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Overrides the keys by the environment looked up by `var`.
+    pub fn apply_env(
+        &mut self,
+        var: impl Fn(&'static str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        if let Some(token) = var("REGEX_SOUP_TOKEN") {
+            self.discord.token = Some(token);
+        }
+        if let Some(id) = parse_env(
+            "REGEX_SOUP_ID",
+            "discord.application_id",
+            var("REGEX_SOUP_ID"),
+        )? {
+            self.discord.application_id = Some(id);
+        }
+        if let Some(secs) = parse_env(
+            "REGEX_SOUP_IDLE_TIMEOUT",
+            "timeouts.idle_secs",
+            var("REGEX_SOUP_IDLE_TIMEOUT"),
+        )? {
+            self.timeouts.idle_secs = secs;
+        }
+        if let Some(path) = var("REGEX_SOUP_FEEDBACK") {
+            self.storage.feedback = PathBuf::from(path);
+        }
+        if let Some(level) = parse_env("REGEX_SOUP_LOG", "logging.level", var("REGEX_SOUP_LOG"))? {
+            self.logging.level = level;
+        }
+        if let Some(format) = parse_env(
+            "REGEX_SOUP_LOG_FORMAT",
+            "logging.format",
+            var("REGEX_SOUP_LOG_FORMAT"),
+        )? {
+            self.logging.format = format;
+        }
+        // the former name is overridden by the current one
+        for &env in &["REGEX_SOUP_METRICS_ADDR", "REGEX_SOUP_STATUS_ADDR"] {
+            if let Some(address) = parse_env(env, "status.address", var(env))? {
+                self.status.address = address;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the ranges of the values.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key, reason: &str| {
+            Err(ConfigError::Invalid {
+                key,
+                reason: reason.to_string(),
+            })
+        };
+        let generation = &self.generation;
+        if !(1..=MAX_SIZE).contains(&generation.default_size) {
+            return invalid(
+                "generation.default_size",
+                &format!("must be between 1 and {MAX_SIZE}"),
+            );
+        }
+        if generation.max_tree_size == 0 {
+            return invalid("generation.max_tree_size", "must be positive");
+        }
+        if !(0.0 <= generation.min_acceptance_rate
+            && generation.min_acceptance_rate < generation.max_acceptance_rate
+            && generation.max_acceptance_rate <= 1.0)
+        {
+            return invalid(
+                "generation.min_acceptance_rate",
+                "must be less than `generation.max_acceptance_rate`, and both must be in [0, 1]",
+            );
+        }
+        if generation.sample_size == 0 {
+            return invalid("generation.sample_size", "must be positive");
+        }
+        // larger sizes may still time out, which gives up the generation
+        let size = generation.default_size();
+        if !is_reachable(
+            &Difficulty(size),
+            &generation.params(),
+            REACHABILITY_ATTEMPTS,
+        ) {
+            return invalid(
+                "generation.default_size",
+                &format!(
+                    "has no problem within {REACHABILITY_ATTEMPTS} candidates of \
+                     `generation.max_tree_size` and the acceptance rates"
+                ),
+            );
+        }
+        let timeouts = &self.timeouts;
+        for (key, secs) in vec![
+            ("timeouts.generation_secs", timeouts.generation_secs),
+            ("timeouts.idle_secs", timeouts.idle_secs),
+            ("timeouts.sweep_secs", timeouts.sweep_secs),
+        ] {
+            if secs == 0 {
+                return invalid(key, "must be positive");
+            }
+        }
+        for (key, path) in vec![
+            ("storage.feedback", &self.storage.feedback),
+            ("storage.guilds", &self.storage.guilds),
+            ("storage.records", &self.storage.records),
+        ] {
+            if path.as_os_str().is_empty() {
+                return invalid(key, "must not be empty");
+            }
+        }
+        Ok(())
+    }
+
+    /// The token and the application id, required by the BOT
+    pub fn credentials(&self) -> Result<(&str, u64), ConfigError> {
+        let token = self.discord.token.as_deref().ok_or(ConfigError::Missing {
+            key: "discord.token",
+            env: "REGEX_SOUP_TOKEN",
+        })?;
+        let application_id = self.discord.application_id.ok_or(ConfigError::Missing {
+            key: "discord.application_id",
+            env: "REGEX_SOUP_ID",
+        })?;
+        Ok((token, application_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, LogFormat, LogLevel};
+    use crate::errors::ConfigError;
+    use indoc::indoc;
+    use std::path::Path;
+
+    fn parse(text: &str) -> Result<Config, ConfigError> {
+        Config::parse(text, Path::new("regexsoup.toml"))
+    }
+
+    #[test]
+    fn example_is_the_default() {
+        let example = include_str!("../regexsoup.example.toml");
+        assert_eq!(parse(example).unwrap(), Config::default());
+        assert_eq!(parse("").unwrap(), Config::default());
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let mut config = parse(indoc! {r#"
+            [discord]
+            token = "from the file"
+            application_id = 1
+            owners = [2]
+
+            [timeouts]
+            idle_secs = 60
+        "#})
+        .unwrap();
+        config
+            .apply_env(|name| match name {
+                "REGEX_SOUP_TOKEN" => Some("from the environment".to_string()),
+                "REGEX_SOUP_LOG" => Some("debug".to_string()),
+                "REGEX_SOUP_LOG_FORMAT" => Some("json".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(config.credentials().unwrap(), ("from the environment", 1));
+        assert!(config.discord.is_owner(2) && !config.discord.is_owner(1));
+        assert_eq!(config.timeouts.idle_secs, 60);
+        assert_eq!(config.logging.level, LogLevel::Debug);
+        assert_eq!(config.logging.format, LogFormat::Json);
+
+        let invalid = config.apply_env(|name| (name == "REGEX_SOUP_ID").then(|| "x".to_string()));
+        assert!(matches!(
+            invalid,
+            Err(ConfigError::Invalid {
+                key: "discord.application_id",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn former_metrics_section_is_accepted() {
+        let mut config = parse(indoc! {r#"
+            [metrics]
+            enabled = false
+            address = "127.0.0.1:9200"
+        "#})
+        .unwrap();
+        assert!(!config.status.enabled);
+        assert_eq!(config.status.address.port(), 9200);
+
+        config
+            .apply_env(|name| {
+                (name == "REGEX_SOUP_METRICS_ADDR").then(|| "0.0.0.0:9300".to_string())
+            })
+            .unwrap();
+        assert_eq!(config.status.address.port(), 9300);
+    }
+
+    #[test]
+    fn invalid_configs_are_reported() {
+        assert!(matches!(
+            parse("[generation]\ndefault_size = 11").unwrap().validate(),
+            Err(ConfigError::Invalid {
+                key: "generation.default_size",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[generation]\nmin_acceptance_rate = 0.9")
+                .unwrap()
+                .validate(),
+            Err(ConfigError::Invalid {
+                key: "generation.min_acceptance_rate",
+                ..
+            })
+        ));
+        // a literal or an epsilon never accepts a quarter of the words
+        assert!(matches!(
+            parse("[generation]\nmax_tree_size = 1").unwrap().validate(),
+            Err(ConfigError::Invalid {
+                key: "generation.default_size",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[timeouts]\nidle_secs = 0").unwrap().validate(),
+            Err(ConfigError::Invalid {
+                key: "timeouts.idle_secs",
+                ..
+            })
+        ));
+        // typos are not ignored
+        assert!(matches!(
+            parse("[timeout]\nidle_secs = 60"),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            parse("[logging]\nlevel = \"loud\""),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            Config::default().credentials(),
+            Err(ConfigError::Missing {
+                key: "discord.token",
+                ..
+            })
+        ));
+    }
+}
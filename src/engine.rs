@@ -0,0 +1,37 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Transport-agnostic game engine
+//!
+//! The engine knows nothing about Discord.
+//! Players and channels are identified by [PlayerId] and [ChannelId],
+//! operations of [Quiz] return transport-neutral [Response](crate::response::Response)s,
+//! and every change of a game is recorded as an [Event] for frontends to deliver.
+
+mod actors;
+mod event;
+mod id;
+mod quiz;
+mod record;
+
+pub use actors::*;
+pub use event::*;
+pub use id::*;
+pub use quiz::*;
+pub use record::*;
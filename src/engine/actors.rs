@@ -0,0 +1,509 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::{ChannelId, GameRecord, Quiz, RecordStore};
+use crate::{metrics::METRICS, regex::RegexAst};
+use dashmap::DashMap;
+use indexmap::IndexMap;
+use std::{
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot};
+
+/// Number of jobs queued for a channel before senders wait
+const MAILBOX_SIZE: usize = 32;
+/// Number of finished games kept for the feedback on them
+pub const RECORDS_KEPT: usize = 1024;
+/// Number of the places whose last answers are kept for `export`
+const ANSWERS_KEPT: usize = 1024;
+
+/// A job run on the game of a channel, inside its task.
+/// It is given `None` instead of the game if the task has stopped before running it.
+type Job = Box<dyn FnOnce(Option<&mut Option<Quiz>>) + Send>;
+
+type Mailboxes<K> = Arc<DashMap<K, mpsc::Sender<Job>>>;
+
+/// Games keyed by the place where they are played, each owned by its own tokio task
+///
+/// Jobs for a channel are sent over mpsc and run one by one by the task of the channel,
+/// and their results are returned over oneshot.
+/// Channels never wait for each other, and a task stops once its game is closed.
+pub struct Actors<K = ChannelId> {
+    mailboxes: Mailboxes<K>,
+    /// answers of the games finished last in the latest [ANSWERS_KEPT] places, oldest first
+    finished: Mutex<IndexMap<K, RegexAst>>,
+    /// the latest finished games
+    records: Mutex<RecordStore>,
+    /// number of the games closed for being idle
+    reaped: AtomicUsize,
+}
+
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> Actors<K> {
+    pub fn new() -> Self {
+        Self::with_records(RecordStore::new(RECORDS_KEPT))
+    }
+
+    /// Keeps the records of finished games in `records`.
+    pub fn with_records(records: RecordStore) -> Self {
+        Self {
+            mailboxes: Arc::new(DashMap::new()),
+            finished: Mutex::new(IndexMap::new()),
+            records: Mutex::new(records),
+            reaped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of the channels running games
+    pub fn len(&self) -> usize {
+        self.mailboxes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mailboxes.is_empty()
+    }
+
+    fn mailbox(&self, key: &K) -> Option<mpsc::Sender<Job>> {
+        // cloned so that no shard of the map is locked across awaits
+        self.mailboxes.get(key).map(|mailbox| mailbox.clone())
+    }
+
+    /// Runs `job` in the task of `key`, and waits for its result.
+    async fn ask<R, F>(mailbox: mpsc::Sender<Job>, job: F) -> Option<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Option<Quiz>) -> R + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move |quiz| {
+            let _ = tx.send(quiz.map(job));
+        });
+        mailbox.send(job).await.ok()?;
+        rx.await.ok().flatten()
+    }
+
+    /// Runs `cmd` on the game of `key`, or returns `None` if no game is running there.
+    pub async fn command<F, R>(&self, key: &K, cmd: F) -> Option<R>
+    where
+        F: FnOnce(&mut Quiz) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let mailbox = self.mailbox(key)?;
+        Self::ask(mailbox, |quiz| quiz.as_mut().map(cmd))
+            .await
+            .flatten()
+    }
+
+    /// Starts `quiz` in `key`, and returns whether it replaces a running game.
+    /// The replaced game is closed in the same way as [finish](Self::finish).
+    pub async fn start(&self, key: K, mut quiz: Quiz) -> bool {
+        loop {
+            let (tx, rx) = oneshot::channel();
+            let job: Job = Box::new(move |slot| {
+                let _ = tx.send(match slot {
+                    Some(slot) => Ok(slot.replace(quiz)),
+                    // handed back to be started again
+                    None => Err(quiz),
+                });
+            });
+            let mailbox = self
+                .mailboxes
+                .entry(key.clone())
+                .or_insert_with(|| spawn(key.clone(), Arc::clone(&self.mailboxes)))
+                .value()
+                .clone();
+            if let Err(mpsc::error::SendError(job)) = mailbox.send(job).await {
+                // the task has stopped since its game was closed
+                self.mailboxes
+                    .remove_if(&key, |_, current| current.same_channel(&mailbox));
+                job(None);
+            }
+            match rx.await {
+                Ok(Ok(replaced)) => {
+                    return match replaced {
+                        Some(replaced) => {
+                            self.close(key, &replaced);
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                Ok(Err(rejected)) => quiz = rejected,
+                // the task has panicked
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Takes the game of `key` if `pred` holds, which stops the task of `key`.
+    async fn take_if<P>(&self, key: &K, pred: P) -> Option<Quiz>
+    where
+        P: FnOnce(&Quiz) -> bool + Send + 'static,
+    {
+        let mailbox = self.mailbox(key)?;
+        let quiz = Self::ask(mailbox.clone(), move |slot| {
+            slot.as_ref().filter(|quiz| pred(quiz))?;
+            slot.take()
+        })
+        .await??;
+        // the task also leaves the map, but it may not have run yet
+        self.mailboxes
+            .remove_if(key, |_, current| current.same_channel(&mailbox));
+        self.close(key.clone(), &quiz);
+        Some(quiz)
+    }
+
+    /// Keeps the answer and the record of a closed game.
+    fn close(&self, key: K, quiz: &Quiz) {
+        let record = quiz.record();
+        METRICS.finished(&record);
+        {
+            let mut finished = self.finished.lock().unwrap();
+            // moved to the back as the latest
+            finished.shift_remove(&key);
+            finished.insert(key, record.answer.clone());
+            while finished.len() > ANSWERS_KEPT {
+                finished.shift_remove_index(0);
+            }
+        }
+        if let Err(why) = self.records.lock().unwrap().insert(record) {
+            tracing::warn!("fail to keep the record: {why:#}");
+        }
+    }
+
+    /// Takes the game of `key` to close it, keeping its answer and its record.
+    pub async fn finish(&self, key: &K) -> Option<Quiz> {
+        self.take_if(key, |_| true).await
+    }
+
+    /// Closes the games idle for `idle` or longer,
+    /// and returns them so that their answers are announced.
+    pub async fn reap(&self, idle: Duration) -> Vec<(K, Quiz)> {
+        let reaped = self.take_all_if(move |quiz| quiz.idle_for() >= idle).await;
+        self.reaped.fetch_add(reaped.len(), Ordering::Relaxed);
+        METRICS.reaped(reaped.len());
+        reaped
+    }
+
+    /// Closes all the games before shutting down, and returns them.
+    pub async fn close_all(&self) -> Vec<(K, Quiz)> {
+        self.take_all_if(|_| true).await
+    }
+
+    async fn take_all_if<P>(&self, pred: P) -> Vec<(K, Quiz)>
+    where
+        P: FnOnce(&Quiz) -> bool + Clone + Send + 'static,
+    {
+        let keys = self
+            .mailboxes
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        let mut taken = vec![];
+        for key in keys {
+            if let Some(quiz) = self.take_if(&key, pred.clone()).await {
+                taken.push((key, quiz));
+            }
+        }
+        taken
+    }
+
+    /// Number of the games closed for being idle so far
+    pub fn reaped(&self) -> usize {
+        self.reaped.load(Ordering::Relaxed)
+    }
+
+    /// The answer of the game finished last in `key`
+    pub fn finished(&self, key: &K) -> Option<RegexAst> {
+        self.finished.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn record(&self, game: u64) -> Option<GameRecord> {
+        self.records.lock().unwrap().get(game).cloned()
+    }
+}
+
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> Default for Actors<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Spawns a task owning a game of `key`, which stops when the game is closed.
+///
+/// The task leaves `mailboxes` before it stops,
+/// and the jobs left in its mailbox are handed back to their senders.
+fn spawn<K>(key: K, mailboxes: Mailboxes<K>) -> mpsc::Sender<Job>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+{
+    let (tx, mut rx) = mpsc::channel::<Job>(MAILBOX_SIZE);
+    let own = tx.clone();
+    tokio::spawn(async move {
+        let mut quiz = None;
+        while let Some(job) = rx.recv().await {
+            job(Some(&mut quiz));
+            if quiz.is_none() {
+                break;
+            }
+        }
+        mailboxes.remove_if(&key, |_, current| current.same_channel(&own));
+        rx.close();
+        while let Some(job) = rx.recv().await {
+            job(None);
+        }
+    });
+    tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Actors, ANSWERS_KEPT};
+    use crate::{
+        engine::{ChannelId, Event, Player, PlayerId, Quiz, Rules},
+        regex::RegexAst,
+        response::Response,
+    };
+    use std::{convert::TryInto, sync::Arc, time::Duration};
+
+    fn quiz(answer: &str) -> Quiz {
+        Quiz::with_answer(
+            RegexAst::parse_str(answer).unwrap(),
+            2u8.try_into().unwrap(),
+            Rules::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn channels_own_their_games() {
+        let actors = Actors::new();
+        let (first, second) = (ChannelId(1), ChannelId(2));
+        assert_eq!(actors.command(&first, |quiz| quiz.id()).await, None);
+
+        assert!(!actors.start(first, quiz("a*")).await);
+        assert!(!actors.start(second, quiz("b*")).await);
+        let replaced = actors.command(&first, |quiz| quiz.id()).await.unwrap();
+        assert!(actors.start(first, quiz("ab")).await);
+        assert_eq!(actors.len(), 2);
+        // the replaced game is closed as well
+        assert_eq!(actors.record(replaced).unwrap().answer.to_string(), "a*");
+        assert_eq!(
+            actors
+                .command(&first, |quiz| quiz.get_answer_regex().to_string())
+                .await,
+            Some("ab".to_string())
+        );
+
+        let id = actors.command(&second, |quiz| quiz.id()).await.unwrap();
+        let finished = actors.finish(&second).await.unwrap();
+        assert_eq!(finished.id(), id);
+        assert_eq!(
+            actors.record(id).unwrap().answer,
+            finished.get_answer_regex()
+        );
+        assert_eq!(actors.finished(&second).unwrap().to_string(), "b*");
+        assert_eq!(actors.command(&second, |quiz| quiz.id()).await, None);
+        assert!(actors.finish(&second).await.is_none());
+        assert_eq!(actors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn idle_games_are_reaped() {
+        let actors = Actors::new();
+        actors.start(ChannelId(1), quiz("a*")).await;
+        assert!(actors.reap(Duration::from_secs(60)).await.is_empty());
+
+        let mut reaped = actors.reap(Duration::ZERO).await;
+        assert_eq!(reaped.len(), 1);
+        let (channel, expired) = &mut reaped[0];
+        assert_eq!(*channel, ChannelId(1));
+        expired.expire();
+        assert_eq!(
+            expired.take_events(),
+            vec![Event::Expired {
+                answer: "a*".to_string()
+            }]
+        );
+        assert!(actors.is_empty());
+        assert_eq!(actors.reaped(), 1);
+        assert_eq!(actors.finished(channel).unwrap().to_string(), "a*");
+
+        // the channel is available again
+        assert!(!actors.start(ChannelId(1), quiz("b*")).await);
+        assert!(actors
+            .command(&ChannelId(1), |quiz| quiz.id())
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn all_games_are_closed_on_shutdown() {
+        let actors = Actors::new();
+        actors.start(ChannelId(1), quiz("a*")).await;
+        actors.start(ChannelId(2), quiz("b*")).await;
+
+        let mut closed = actors.close_all().await;
+        closed.sort_by_key(|(channel, _)| channel.0);
+        assert_eq!(
+            closed[1].1.shut_down(),
+            "This REGEX-SOUP is closed since the BOT is shutting down. The answer was `b*` (size \
+             2)."
+        );
+        assert_eq!(closed.len(), 2);
+        assert!(actors.is_empty());
+        // not counted as idle
+        assert_eq!(actors.reaped(), 0);
+    }
+
+    #[tokio::test]
+    async fn guesses_are_judged_out_of_the_games() {
+        let actors = Actors::new();
+        let channel = ChannelId(1);
+        actors.start(channel, quiz("(a|b)*")).await;
+        let alice = Player {
+            id: PlayerId(1),
+            name: "alice".to_string(),
+        };
+
+        for (input, expected) in [("a*", "a* => WA"), ("(a*b*)*", "(a*b*)* => AC")].iter() {
+            let guess = actors
+                .command(&channel, move |quiz| quiz.guess(input))
+                .await
+                .unwrap()
+                .unwrap();
+            let verdict = guess.judge_blocking().await.unwrap();
+            // the game keeps running meanwhile
+            assert!(actors.command(&channel, |quiz| quiz.id()).await.is_some());
+            let alice = alice.clone();
+            let response = actors
+                .command(&channel, move |quiz| quiz.settle(&alice, verdict).unwrap())
+                .await;
+            assert_eq!(response, Some(Response::message(*expected)));
+        }
+        assert_eq!(
+            actors.command(&channel, |quiz| quiz.is_solved()).await,
+            Some(true)
+        );
+
+        // a verdict on a replaced game is not settled
+        let guess = actors
+            .command(&channel, |quiz| quiz.guess("a*"))
+            .await
+            .unwrap()
+            .unwrap();
+        actors.start(channel, quiz("a*")).await;
+        let verdict = guess.judge_blocking().await.unwrap();
+        let settled = actors
+            .command(&channel, move |quiz| quiz.settle(&alice, verdict).is_ok())
+            .await;
+        assert_eq!(settled, Some(false));
+    }
+
+    #[test]
+    fn only_the_latest_answers_are_kept() {
+        let actors = Actors::new();
+        for key in 0..=ANSWERS_KEPT as u64 {
+            actors.close(ChannelId(key), &quiz("a*"));
+        }
+        // finished again, which makes it the latest
+        actors.close(ChannelId(1), &quiz("b*"));
+        actors.close(ChannelId(ANSWERS_KEPT as u64 + 1), &quiz("a*"));
+
+        assert!(actors.finished(&ChannelId(0)).is_none());
+        assert!(actors.finished(&ChannelId(2)).is_none());
+        assert_eq!(actors.finished(&ChannelId(1)).unwrap().to_string(), "b*");
+        assert_eq!(actors.finished.lock().unwrap().len(), ANSWERS_KEPT);
+    }
+
+    #[tokio::test]
+    async fn concurrent_commands_are_not_lost() {
+        let actors = Arc::new(Actors::new());
+        let channel = ChannelId(1);
+        actors.start(channel, quiz("a*")).await;
+
+        let joins = (0..8)
+            .map(|id| {
+                let actors = Arc::clone(&actors);
+                tokio::spawn(async move {
+                    actors
+                        .command(&channel, move |quiz| quiz.register(PlayerId(id)).is_ok())
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        for join in joins {
+            assert_eq!(join.await.unwrap(), Some(true));
+        }
+        let registered = actors
+            .command(&channel, |quiz| {
+                (0..8).all(|id| quiz.is_participant(&PlayerId(id)))
+            })
+            .await;
+        assert_eq!(registered, Some(true));
+    }
+
+    #[tokio::test]
+    async fn start_queued_behind_finish_is_not_lost() {
+        let actors = Actors::new();
+        let channel = ChannelId(1);
+        for _ in 0..8 {
+            actors.start(channel, quiz("a*")).await;
+            let next = quiz("b*");
+            let id = next.id();
+            // the start is queued in the mailbox of the task stopped by the finish
+            let (finished, _) = tokio::join!(actors.finish(&channel), actors.start(channel, next));
+            assert_eq!(finished.unwrap().get_answer_regex().to_string(), "a*");
+            assert_eq!(actors.command(&channel, |quiz| quiz.id()).await, Some(id));
+            assert_eq!(actors.len(), 1);
+            actors.finish(&channel).await;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn finish_races_with_start() {
+        let actors = Arc::new(Actors::new());
+        let channel = ChannelId(1);
+        for _ in 0..64 {
+            actors.start(channel, quiz("a*")).await;
+            let next = quiz("b*");
+            let id = next.id();
+            let finishing = tokio::spawn({
+                let actors = Arc::clone(&actors);
+                async move { actors.finish(&channel).await.map(|quiz| quiz.id()) }
+            });
+            let starting = tokio::spawn({
+                let actors = Arc::clone(&actors);
+                async move { actors.start(channel, next).await }
+            });
+            let (finished, _) = (finishing.await.unwrap(), starting.await.unwrap());
+            // the new game is either running or closed, but never lost
+            if finished == Some(id) {
+                assert!(actors.record(id).is_some());
+            } else {
+                assert_eq!(actors.command(&channel, |quiz| quiz.id()).await, Some(id));
+                actors.finish(&channel).await;
+            }
+            assert!(actors.is_empty());
+        }
+    }
+}
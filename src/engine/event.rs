@@ -0,0 +1,69 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::PlayerId;
+use serde::Serialize;
+
+/// Changes of a game, delivered to the frontends
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Joined {
+        player: PlayerId,
+    },
+    Queried {
+        player: PlayerId,
+        input: String,
+        /// `None` in private queries
+        #[serde(skip_serializing_if = "Option::is_none")]
+        is_match: Option<bool>,
+    },
+    Guessed {
+        player: PlayerId,
+        input: String,
+        accepted: bool,
+    },
+    /// The game has been won by an AC in the normal mode.
+    Solved {
+        player: PlayerId,
+        answer: String,
+    },
+    GaveUp {
+        player: PlayerId,
+    },
+    /// Everyone has given up and the answer has been revealed.
+    Revealed {
+        answer: String,
+    },
+    /// The game has been closed for being idle, revealing the answer.
+    Expired {
+        answer: String,
+    },
+    /// The game has been stopped by a moderator or by the shutdown, revealing the answer.
+    Stopped {
+        answer: String,
+    },
+    /// A moderator has removed the player from the participants.
+    Kicked {
+        player: PlayerId,
+    },
+    TurnChanged {
+        player: PlayerId,
+    },
+}
@@ -0,0 +1,1166 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::{Event, GameRecord, Player, PlayerId};
+use crate::{
+    errors::CommandError,
+    regex::{
+        randomly_generate_until, Alphabet, Difficulty, GenerationParams, Neutral, ProblemScorer,
+        RegexAst,
+    },
+    response::{Button, ButtonStyle, Buttons, Colour, Component, CustomId, Embed, Response, Vote},
+};
+use anyhow::anyhow;
+use indexmap::{indexmap, indexset, IndexMap, IndexSet};
+use indoc::indoc;
+use itertools::Itertools;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    convert::TryInto,
+    num::NonZeroU8,
+    time::{Duration, Instant},
+};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Rules of a game
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, EnumIter, EnumString, Display, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    /// The game ends with the first AC.
+    Normal,
+    /// The game continues after ACs, and every AC is scored by its [size](RegexAst::size).
+    Golf,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Normal
+    }
+}
+
+/// Options of a game chosen at the start
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub mode: GameMode,
+    /// Whether participants take turns to make a query or a guess
+    pub turn_based: bool,
+    /// Whether the results of a query are only visible to the querier
+    pub private_queries: bool,
+    /// Maximum number of distinct queries in the game
+    pub query_budget: Option<usize>,
+    /// Time limit for each turn of a turn-based game
+    pub turn_time_limit: Duration,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            mode: GameMode::default(),
+            turn_based: false,
+            private_queries: false,
+            query_budget: None,
+            turn_time_limit: TURN_TIME_LIMIT,
+        }
+    }
+}
+
+/// Default time limit for each turn of a turn-based game
+pub const TURN_TIME_LIMIT: Duration = Duration::from_secs(60);
+
+/// Rotation of a turn-based game in the order of participation
+struct Rotation {
+    /// index of the current player in `participants`
+    current: usize,
+    deadline: Instant,
+}
+
+impl Rotation {
+    fn new(limit: Duration) -> Self {
+        Rotation {
+            current: 0,
+            deadline: Instant::now() + limit,
+        }
+    }
+}
+
+/// The current turn of a turn-based game
+#[derive(Copy, Clone, Debug)]
+pub struct Turn {
+    pub player: PlayerId,
+    pub deadline: Instant,
+    /// time limit of each turn
+    pub limit: Duration,
+}
+
+/// Maximum length of suffixes suggested by [Quiz::unasked_words]
+const MAX_SUGGESTED_SUFFIX: usize = 8;
+
+// constants related to scoring of golf
+const GOLF_BASE_SCORE: usize = 100;
+const GOLF_BONUS: usize = 50;
+
+/// The smallest accepted guess of a player in golf
+#[derive(Clone, Debug)]
+pub struct GolfRecord {
+    pub name: String,
+    pub regex: String,
+    pub size: usize,
+}
+
+impl GolfRecord {
+    /// Score relative to the size of the answer.
+    /// Guesses as small as the answer gain 100 points,
+    /// and those smaller than the answer gain 50 bonus points in addition.
+    pub fn score(&self, answer_size: usize) -> usize {
+        let bonus = if self.size < answer_size {
+            GOLF_BONUS
+        } else {
+            0
+        };
+        GOLF_BASE_SCORE * answer_size / self.size.max(1) + bonus
+    }
+}
+
+pub struct Quiz {
+    /// identifies the game among those played in the same channel
+    id: u64,
+    size: u8,
+    rules: Rules,
+    regex: RegexAst,
+    /// seed of the generation, if generated
+    seed: Option<u64>,
+    /// candidates generated until the answer was accepted, or 0 if not generated
+    attempts: usize,
+    started: Instant,
+    /// when a player acted last
+    last_activity: Instant,
+    /// histories of queries keyed by the querier in private queries, or `None` otherwise
+    histories: IndexMap<Option<PlayerId>, IndexMap<String, String>>,
+    /// syntactically valid guesses in the order of submission
+    guesses: Vec<String>,
+    participants: IndexSet<PlayerId>,
+    golf: IndexMap<PlayerId, GolfRecord>,
+    rotation: Rotation,
+    /// whether someone has got AC in the normal mode
+    solved: bool,
+    /// events not yet taken by the frontend
+    events: Vec<Event>,
+}
+
+/// A guess of a valid syntax and domain, which is not judged yet
+pub struct Guess {
+    game: u64,
+    input: String,
+    ast: RegexAst,
+    answer: RegexAst,
+}
+
+impl Guess {
+    /// Checks the equivalence to the answer, which is CPU-bound.
+    pub fn judge(self) -> Verdict {
+        Verdict {
+            game: self.game,
+            accepted: self.answer.equivalent_to(&self.ast),
+            size: self.ast.size(),
+            input: self.input,
+        }
+    }
+
+    /// [Judges](Guess::judge) on the blocking threads, so that the async workers keep running.
+    pub async fn judge_blocking(self) -> anyhow::Result<Verdict> {
+        Ok(tokio::task::spawn_blocking(move || self.judge()).await?)
+    }
+}
+
+/// Whether a [Guess] is accepted, to be [settled](Quiz::settle) in its game
+pub struct Verdict {
+    game: u64,
+    input: String,
+    size: usize,
+    accepted: bool,
+}
+
+pub enum InspectionAcceptance {
+    Accepted(String),
+    WrongAnswer(String),
+    /// AC in golf, ranked by the best record of the player
+    Golfed {
+        input: String,
+        size: usize,
+        /// size of the best record, which may be smaller than `size`
+        best: usize,
+        rank: usize,
+        answer_size: usize,
+    },
+}
+
+impl From<InspectionAcceptance> for Response {
+    fn from(acceptance: InspectionAcceptance) -> Self {
+        Response::message(acceptance.to_string())
+    }
+}
+
+impl ToString for InspectionAcceptance {
+    fn to_string(&self) -> String {
+        match self {
+            InspectionAcceptance::Accepted(input) => format!("{input} => AC"),
+            InspectionAcceptance::WrongAnswer(input) => format!("{input} => WA"),
+            InspectionAcceptance::Golfed {
+                input,
+                size,
+                best,
+                rank,
+                answer_size,
+            } => {
+                let comparison = match size.cmp(answer_size) {
+                    Ordering::Less => format!(
+                        "shorter than the answer (size {answer_size}), +{GOLF_BONUS} bonus!"
+                    ),
+                    Ordering::Equal => format!("as short as the answer (size {answer_size})"),
+                    Ordering::Greater => format!("longer than the answer (size {answer_size})"),
+                };
+                if best < size {
+                    format!(
+                        "{input} => AC (size {size}; your best is size {best}, rank #{rank}): \
+                         {comparison}"
+                    )
+                } else {
+                    format!("{input} => AC (size {size}, rank #{rank}): {comparison}")
+                }
+            }
+        }
+    }
+}
+
+pub enum QueryMatch {
+    Yes(String),
+    No(String),
+}
+
+impl From<QueryMatch> for Response {
+    fn from(query_match: QueryMatch) -> Self {
+        Response::message(query_match.to_string())
+    }
+}
+
+impl ToString for QueryMatch {
+    fn to_string(&self) -> String {
+        match self {
+            QueryMatch::Yes(input) => format!("{input} => Yes"),
+            QueryMatch::No(input) => format!("{input} => No"),
+        }
+    }
+}
+
+impl Quiz {
+    pub fn new() -> Self {
+        Self::new_with_difficulty(3u8.try_into().unwrap(), Rules::default())
+    }
+
+    pub fn new_with_difficulty(difficulty: NonZeroU8, rules: Rules) -> Self {
+        Self::generate(difficulty, rules, &GenerationParams::default(), &Neutral)
+    }
+
+    /// Generates the answer from a random seed, preferring problems scored high by `scorer`.
+    pub fn generate<S: ProblemScorer + ?Sized>(
+        difficulty: NonZeroU8,
+        rules: Rules,
+        params: &GenerationParams,
+        scorer: &S,
+    ) -> Self {
+        Self::generate_until(difficulty, rules, params, scorer, |_| false).expect("never given up")
+    }
+
+    /// Same as [generate](Self::generate), but gives up with `None`
+    /// once `give_up` holds for the number of the candidates generated so far.
+    pub fn generate_until<S, G>(
+        difficulty: NonZeroU8,
+        rules: Rules,
+        params: &GenerationParams,
+        scorer: &S,
+        give_up: G,
+    ) -> Option<Self>
+    where
+        S: ProblemScorer + ?Sized,
+        G: FnMut(usize) -> bool,
+    {
+        let seed = rand::random();
+        let (regex, attempts) = randomly_generate_until(
+            &Difficulty(difficulty),
+            params,
+            scorer,
+            &mut StdRng::seed_from_u64(seed),
+            give_up,
+        )?;
+        Some(Self {
+            seed: Some(seed),
+            attempts,
+            ..Self::with_answer(regex, difficulty, rules)
+        })
+    }
+
+    /// Creates a game whose answer is `regex`.
+    pub fn with_answer(regex: RegexAst, difficulty: NonZeroU8, rules: Rules) -> Self {
+        Self {
+            id: rand::random(),
+            size: difficulty.into(),
+            rules,
+            regex,
+            seed: None,
+            attempts: 0,
+            started: Instant::now(),
+            last_activity: Instant::now(),
+            histories: indexmap! {},
+            guesses: vec![],
+            participants: indexset! {},
+            golf: indexmap! {},
+            rotation: Rotation::new(rules.turn_time_limit),
+            solved: false,
+            events: vec![],
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Record of the game, kept after it finishes.
+    pub fn record(&self) -> GameRecord {
+        GameRecord {
+            id: self.id,
+            answer: self.regex.clone(),
+            size: self.size,
+            seed: self.seed,
+            queries: self.len(),
+            guesses: self.guesses.len(),
+            duration: self.started.elapsed(),
+            solved: self.solved || !self.golf.is_empty(),
+        }
+    }
+
+    /// Number of the candidates generated until the answer was accepted
+    pub fn generation_attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Takes the events occurred since the last call.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Announcement of a fresh game
+    pub fn introduction(&self) -> Embed {
+        let domain = Alphabet::iter()
+            .take(self.size.into())
+            .collect::<HashSet<_>>();
+        let mut embed = Embed::new(Colour::BlitzBlue, "Starts a fresh REGEX-SOUP")
+            .field("domain", format!("Σ = {domain:?}"), false)
+            .field("mode", self.rules.mode, false);
+        if self.rules.private_queries {
+            embed = embed.field(
+                "queries",
+                "private: results of `/query` are only visible to the querier",
+                false,
+            );
+        }
+        if let Some(budget) = self.rules.query_budget {
+            embed = embed.field("budget", format!("{budget} queries in total"), false);
+        }
+        if self.rules.turn_based {
+            embed.field(
+                "turns",
+                format!(
+                    "`/join` to take turns in the order of participation ({}s per turn)",
+                    self.rules.turn_time_limit.as_secs()
+                ),
+                false,
+            )
+        } else {
+            embed
+        }
+    }
+
+    pub fn query(&mut self, player: PlayerId, input: &str) -> anyhow::Result<Response> {
+        self.last_activity = Instant::now();
+        let alphabets = if input.eq(r#""""#) {
+            vec![]
+        } else {
+            Alphabet::vec_from_str(input)?
+        };
+        self.validate(&alphabets)?;
+        // `B` and `b` are the same query
+        let input = &if alphabets.is_empty() {
+            r#""""#.to_string()
+        } else {
+            Alphabet::slice_to_plain_string(&alphabets)
+        };
+        let asked = self
+            .history(player)
+            .any(|(query, _)| query.as_str() == input);
+        match self.rules.query_budget {
+            Some(budget) if !asked && self.len() >= budget => {
+                return Err(CommandError::QueryBudget { budget }.into())
+            }
+            _ => {}
+        }
+        let is_match = self.regex.matches(&alphabets);
+        self.histories
+            .entry(self.history_key(player))
+            .or_default()
+            .entry(input.to_string())
+            .or_insert((if is_match { "Yes" } else { "No" }).to_string());
+        self.events.push(Event::Queried {
+            player,
+            input: input.to_string(),
+            // the result is not announced in private queries
+            is_match: (!self.rules.private_queries).then(|| is_match),
+        });
+        if is_match {
+            Ok(QueryMatch::Yes(input.to_string()).into())
+        } else {
+            Ok(QueryMatch::No(input.to_string()).into())
+        }
+    }
+
+    /// Judges `input` at once, which blocks the game during the equivalence check.
+    pub fn inspect(&mut self, player: &Player, input: &str) -> anyhow::Result<Response> {
+        let verdict = self.guess(input)?.judge();
+        self.settle(player, verdict)
+    }
+
+    /// Checks `input` against the domain, to be [judged](Guess::judge) out of the game.
+    pub fn guess(&self, input: &str) -> anyhow::Result<Guess> {
+        let ast = RegexAst::parse_str(input)?;
+        let alphabets = ast.used_alphabets().iter().cloned().collect_vec();
+        self.validate(&alphabets)?;
+        Ok(Guess {
+            game: self.id,
+            input: input.to_string(),
+            ast,
+            answer: self.regex.clone(),
+        })
+    }
+
+    /// Applies the `verdict` on a guess of `player` to the game.
+    pub fn settle(&mut self, player: &Player, verdict: Verdict) -> anyhow::Result<Response> {
+        if verdict.game != self.id {
+            return Err(anyhow!("The game has changed while judging the guess."));
+        }
+        let Verdict {
+            input,
+            size,
+            accepted,
+            ..
+        } = verdict;
+        let input = input.as_str();
+        self.last_activity = Instant::now();
+        self.guesses.push(input.to_string());
+        self.events.push(Event::Guessed {
+            player: player.id,
+            input: input.to_string(),
+            accepted,
+        });
+        if !accepted {
+            return Ok(InspectionAcceptance::WrongAnswer(input.to_string()).into());
+        }
+        match self.rules.mode {
+            GameMode::Normal => {
+                self.solved = true;
+                self.events.push(Event::Solved {
+                    player: player.id,
+                    answer: self.regex.to_string(),
+                });
+                Ok(InspectionAcceptance::Accepted(input.to_string()).into())
+            }
+            GameMode::Golf => {
+                let record = GolfRecord {
+                    name: player.name.clone(),
+                    regex: input.to_string(),
+                    size,
+                };
+                self.golf
+                    .entry(player.id)
+                    .and_modify(|best| {
+                        if size < best.size {
+                            *best = record.clone();
+                        }
+                    })
+                    .or_insert(record);
+                let (rank, best) = self
+                    .golf_ranking()
+                    .iter()
+                    .enumerate()
+                    .find(|(_, (id, _))| **id == player.id)
+                    .map_or((1, size), |(position, (_, best))| (position + 1, best.size));
+                Ok(InspectionAcceptance::Golfed {
+                    input: input.to_string(),
+                    size,
+                    best,
+                    rank,
+                    answer_size: self.regex.size(),
+                }
+                .into())
+            }
+        }
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// Whether the game has been won by an AC.
+    pub fn is_solved(&self) -> bool {
+        self.solved
+    }
+
+    /// The current turn, if the game is turn-based and someone has joined.
+    pub fn turn(&self) -> Option<Turn> {
+        if !self.rules.turn_based {
+            return None;
+        }
+        self.participants
+            .get_index(self.rotation.current)
+            .map(|player| Turn {
+                player: *player,
+                deadline: self.rotation.deadline,
+                limit: self.rules.turn_time_limit,
+            })
+    }
+
+    /// Checks that `player` may make a query or a guess now.
+    pub fn check_turn(&self, player: &PlayerId) -> Result<(), CommandError> {
+        match self.turn() {
+            Some(turn) if turn.player != *player => Err(CommandError::OutOfTurn {
+                current: turn.player.0,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn pass_turn(&mut self) {
+        self.rotation.current = match self.participants.len() {
+            0 => 0,
+            len => (self.rotation.current + 1) % len,
+        };
+        self.rotation.deadline = Instant::now() + self.rules.turn_time_limit;
+        self.push_turn_changed();
+    }
+
+    fn push_turn_changed(&mut self) {
+        if let Some(turn) = self.turn() {
+            self.events.push(Event::TurnChanged {
+                player: turn.player,
+            });
+        }
+    }
+
+    /// Passes the turn to the next player and returns the new turn.
+    pub fn end_turn(&mut self) -> Option<Turn> {
+        self.pass_turn();
+        self.turn()
+    }
+
+    /// Passes the turn to the next player if the current player has run out of time.
+    /// Returns the skipped turn and the new turn.
+    pub fn skip_expired_turn(&mut self) -> Option<(Turn, Turn)> {
+        let expired = self.turn().filter(|turn| turn.deadline <= Instant::now())?;
+        self.pass_turn();
+        self.turn().map(|turn| (expired, turn))
+    }
+
+    /// Records of golf sorted by size.
+    /// Records of the same size are ordered by the time of the first AC.
+    fn golf_ranking(&self) -> Vec<(&PlayerId, &GolfRecord)> {
+        self.golf
+            .iter()
+            .sorted_by_key(|(_, record)| record.size)
+            .collect()
+    }
+
+    /// Standings of golf as a text.
+    pub fn golf_standings(&self) -> String {
+        let answer_size = self.regex.size();
+        if self.golf.is_empty() {
+            return "No one has solved yet.".to_string();
+        }
+        self.golf_ranking()
+            .iter()
+            .enumerate()
+            .map(|(index, (_, record))| {
+                format!(
+                    "#{} {}: `{}` (size {}, {} pts)",
+                    index + 1,
+                    record.name,
+                    record.regex,
+                    record.size,
+                    record.score(answer_size)
+                )
+            })
+            .join("\n")
+    }
+
+    /// Registers `player` as a participant.
+    /// Returns the new turn if the turn has come to `player` immediately.
+    pub fn register(&mut self, player: PlayerId) -> anyhow::Result<Option<Turn>> {
+        self.last_activity = Instant::now();
+        self.participants
+            .insert(player)
+            .then(|| ())
+            .ok_or_else(|| anyhow!("already registered."))?;
+        self.events.push(Event::Joined { player });
+        if self.participants.len() == 1 {
+            self.rotation = Rotation::new(self.rules.turn_time_limit);
+            self.push_turn_changed();
+            Ok(self.turn())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Removes `player` from the participants, recording `event`.
+    /// The turn passes to the next player if it was the turn of `player`.
+    fn leave(&mut self, player: &PlayerId, event: Event) -> anyhow::Result<()> {
+        let index = self
+            .participants
+            .get_index_of(player)
+            .ok_or_else(|| anyhow!("not registered"))?;
+        // `shift_remove` keeps the order of the rotation
+        self.participants.shift_remove_index(index);
+        self.events.push(event);
+        if index < self.rotation.current {
+            self.rotation.current -= 1;
+        } else if index == self.rotation.current {
+            // the turn passes to the next player, who now has the same index
+            self.rotation.current = match self.participants.len() {
+                0 => 0,
+                len => self.rotation.current % len,
+            };
+            self.rotation.deadline = Instant::now() + self.rules.turn_time_limit;
+            self.push_turn_changed();
+        }
+        Ok(())
+    }
+
+    /// Removes `player` from the participants.
+    /// When no one is left, the answer is revealed with buttons of feedback.
+    pub fn accepts_give_up(&mut self, player: &Player) -> anyhow::Result<Response> {
+        self.last_activity = Instant::now();
+        self.leave(&player.id, Event::GaveUp { player: player.id })?;
+        if self.participants.is_empty() {
+            self.events.push(Event::Revealed {
+                answer: self.regex.to_string(),
+            });
+        }
+        Ok(self
+            .participants
+            .is_empty()
+            .then(|| {
+                let good = Button {
+                    style: ButtonStyle::Success,
+                    label: "Good".to_string(),
+                    custom_id: CustomId::Feedback {
+                        vote: Vote::Good,
+                        game: self.id,
+                    }
+                    .to_string(),
+                };
+                let bad = Button {
+                    style: ButtonStyle::Danger,
+                    label: "Bad".to_string(),
+                    custom_id: CustomId::Feedback {
+                        vote: Vote::Bad,
+                        game: self.id,
+                    }
+                    .to_string(),
+                };
+
+                Component::Buttons {
+                    content: format!(
+                        "{}Was the regular expression interesting as a problem?\n",
+                        self.revelation()
+                    ),
+                    buttons: Buttons::new([good, bad]),
+                }
+                .into()
+            })
+            .unwrap_or_else(|| Response::message(format!("{} is removed.", &player.name))))
+    }
+
+    /// The answer and the standings, shown when no one is left.
+    pub fn revelation(&self) -> String {
+        let standings = match self.rules.mode {
+            GameMode::Normal => String::new(),
+            GameMode::Golf => format!("Standings:\n{}\n", self.golf_standings()),
+        };
+        format!(
+            indoc! {r#"
+                There is no longer a challenger.
+                The answer is `{}` (size {}).
+                {}"#},
+            self.regex.simplify(),
+            self.regex.size(),
+            standings
+        )
+    }
+
+    /// Removes `player` from the participants by a moderator.
+    /// Unlike [accepts_give_up](Self::accepts_give_up), the game goes on without anyone.
+    pub fn kick(&mut self, player: PlayerId) -> anyhow::Result<String> {
+        self.leave(&player, Event::Kicked { player })?;
+        Ok(format!("<@{}> is removed from the participants.", player.0))
+    }
+
+    /// Reveals the answer of the game stopped by a moderator, and returns the announcement.
+    pub fn stop(&mut self) -> String {
+        self.stop_because("stopped by a moderator")
+    }
+
+    /// Reveals the answer of the game closed before the process exits, and returns the announcement.
+    pub fn shut_down(&mut self) -> String {
+        self.stop_because("closed since the BOT is shutting down")
+    }
+
+    fn stop_because(&mut self, reason: &str) -> String {
+        self.events.push(Event::Stopped {
+            answer: self.regex.to_string(),
+        });
+        format!(
+            "This REGEX-SOUP is {reason}. The answer was `{}` (size {}).",
+            self.regex.simplify(),
+            self.regex.size()
+        )
+    }
+
+    /// Time since a player acted last
+    pub fn idle_for(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    /// Reveals the answer of the game closed for being idle, and returns the announcement.
+    pub fn expire(&mut self) -> String {
+        self.events.push(Event::Expired {
+            answer: self.regex.to_string(),
+        });
+        format!(
+            "This REGEX-SOUP is closed since nobody has played for {} minutes. The answer was \
+             `{}`.",
+            self.idle_for().as_secs() / 60,
+            self.regex.simplify()
+        )
+    }
+
+    fn history_key(&self, player: PlayerId) -> Option<PlayerId> {
+        self.rules.private_queries.then(|| player)
+    }
+
+    fn history(&self, player: PlayerId) -> impl Iterator<Item = (&String, &String)> + '_ {
+        self.histories
+            .get(&self.history_key(player))
+            .into_iter()
+            .flatten()
+    }
+
+    /// History of queries visible to `player`.
+    pub fn get_query_history(&self, player: PlayerId) -> Response {
+        let mut embed = Embed::new(Colour::DarkBlue, "query history");
+        if self.history(player).next().is_none() {
+            embed = embed.field("Nothing to show", "-", false);
+        }
+        for (query, result) in self.history(player) {
+            embed = embed.field(query.eq("").then(|| "ε").unwrap_or(query), result, true);
+        }
+        if self.rules.mode == GameMode::Golf {
+            embed = embed.field("golf standings", self.golf_standings(), false);
+        }
+        embed.into()
+    }
+
+    pub fn is_participant(&self, id: &PlayerId) -> bool {
+        self.participants.contains(id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.participants.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.histories.values().map(IndexMap::len).sum()
+    }
+
+    pub fn get_answer_regex(&self) -> RegexAst {
+        self.regex.clone()
+    }
+
+    /// Words starting with `prefix` which `player` has not seen queried yet, in the shortlex order.
+    /// The empty word is denoted by `""` as in [query](Self::query).
+    pub fn unasked_words(&self, player: PlayerId, prefix: &str, limit: usize) -> Vec<String> {
+        let prefix = match Alphabet::vec_from_str(prefix) {
+            Ok(prefix) if self.validate(&prefix).is_ok() => prefix,
+            _ => return vec![],
+        };
+        let asked = self
+            .history(player)
+            .filter_map(|(query, _)| match query.as_str() {
+                r#""""# => Some(vec![]),
+                query => Alphabet::vec_from_str(query).ok(),
+            })
+            .collect::<HashSet<_>>();
+        let domain = Alphabet::iter().take(self.size.into()).collect_vec();
+
+        let mut words = vec![];
+        let mut level = vec![prefix];
+        for _ in 0..=MAX_SUGGESTED_SUFFIX {
+            for word in level.iter().filter(|word| !asked.contains(*word)) {
+                words.push(match word.as_slice() {
+                    [] => r#""""#.to_string(),
+                    word => Alphabet::slice_to_plain_string(word),
+                });
+                if words.len() >= limit {
+                    return words;
+                }
+            }
+            level = level
+                .iter()
+                .cartesian_product(&domain)
+                .map(|(word, alphabet)| [&word[..], &[*alphabet]].concat())
+                .collect();
+        }
+        words
+    }
+
+    /// Distinct guesses, the most recent first.
+    pub fn recent_guesses(&self, limit: usize) -> Vec<String> {
+        self.guesses
+            .iter()
+            .rev()
+            .unique()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn validate(&self, input: &[Alphabet]) -> anyhow::Result<()> {
+        let domain = Alphabet::iter().take(self.size.into()).collect_vec();
+        let invalid = input.iter().filter(|c| !domain.contains(c)).collect_vec();
+        invalid.is_empty().then(|| ()).ok_or_else(|| {
+            anyhow!(
+                indoc::indoc! {"
+                    Domain Error: {:?} {}.
+                    Valid Alphabets are {:?}.
+                "},
+                invalid,
+                if invalid.len() == 1 {
+                    "is not a valid Alphabet"
+                } else {
+                    "are not valid Alphabets"
+                },
+                domain
+            )
+        })
+    }
+}
+
+impl Default for Quiz {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameMode, Quiz, Rules};
+    use crate::{
+        engine::{Event, Player, PlayerId},
+        errors::CommandError,
+        regex::RegexAst,
+        response::{Colour, Component, Embed, Message, Response},
+    };
+    use std::convert::TryInto;
+
+    fn quiz(regex: &str, rules: Rules) -> Quiz {
+        let answer = RegexAst::parse_str(regex).unwrap();
+        Quiz::with_answer(answer, 2u8.try_into().unwrap(), rules)
+    }
+
+    fn player(id: u64) -> Player {
+        Player {
+            id: PlayerId(id),
+            name: format!("player{id}"),
+        }
+    }
+
+    #[test]
+    fn quiz_responses() {
+        let mut quiz = quiz("(a|b)*a", Rules::default());
+        let alice = player(1);
+
+        assert!(quiz.register(alice.id).is_ok());
+        assert!(quiz.register(alice.id).is_err());
+
+        assert_eq!(
+            quiz.query(alice.id, "ba").unwrap(),
+            Response::message("ba => Yes")
+        );
+        assert_eq!(
+            quiz.query(alice.id, "ab").unwrap(),
+            Response::message("ab => No")
+        );
+        assert!(quiz.query(alice.id, "abc").is_err());
+
+        assert_eq!(
+            quiz.get_query_history(alice.id),
+            Response::from(
+                Embed::new(Colour::DarkBlue, "query history")
+                    .field("ba", "Yes", true)
+                    .field("ab", "No", true)
+            )
+        );
+
+        assert_eq!(
+            quiz.inspect(&alice, "a*").unwrap(),
+            Response::message("a* => WA")
+        );
+        assert!(!quiz.is_solved());
+        assert_eq!(
+            quiz.inspect(&alice, "(b|a)*a").unwrap(),
+            Response::message("(b|a)*a => AC")
+        );
+        assert!(quiz.is_solved());
+
+        assert_eq!(
+            quiz.take_events(),
+            vec![
+                Event::Joined { player: alice.id },
+                Event::Queried {
+                    player: alice.id,
+                    input: "ba".to_string(),
+                    is_match: Some(true),
+                },
+                Event::Queried {
+                    player: alice.id,
+                    input: "ab".to_string(),
+                    is_match: Some(false),
+                },
+                Event::Guessed {
+                    player: alice.id,
+                    input: "a*".to_string(),
+                    accepted: false,
+                },
+                Event::Guessed {
+                    player: alice.id,
+                    input: "(b|a)*a".to_string(),
+                    accepted: true,
+                },
+                Event::Solved {
+                    player: alice.id,
+                    answer: "(a|b)*a".to_string(),
+                },
+            ]
+        );
+        assert!(quiz.take_events().is_empty());
+    }
+
+    #[test]
+    fn golf_replies_rank_the_best_records() {
+        let rules = Rules {
+            mode: GameMode::Golf,
+            ..Rules::default()
+        };
+        let mut quiz = quiz("(a|b)*", rules);
+        let (alice, bob) = (player(1), player(2));
+        let size = |regex| RegexAst::parse_str(regex).unwrap().size();
+        let reply = |quiz: &mut Quiz, player, regex| match quiz.inspect(player, regex).unwrap() {
+            Response::Message(Message::String(reply)) => reply,
+            response => panic!("{response:?}"),
+        };
+
+        let (short, long) = ("(a|b)*", "(a*b*)*");
+        assert!(
+            reply(&mut quiz, &alice, short).contains(&format!("(size {}, rank #1)", size(short)))
+        );
+        assert!(reply(&mut quiz, &bob, long).contains(&format!("(size {}, rank #2)", size(long))));
+        // a worse resubmission reports the best record with its rank
+        assert!(reply(&mut quiz, &alice, long).contains(&format!(
+            "(size {}; your best is size {}, rank #1)",
+            size(long),
+            size(short)
+        )));
+        assert!(!quiz.is_solved());
+    }
+
+    #[test]
+    fn quiz_turns_and_give_up() {
+        let rules = Rules {
+            turn_based: true,
+            ..Rules::default()
+        };
+        let mut quiz = quiz("ab", rules);
+        let (alice, bob) = (player(1), player(2));
+
+        assert_eq!(
+            quiz.register(alice.id).unwrap().map(|turn| turn.player),
+            Some(alice.id)
+        );
+        assert!(quiz.register(bob.id).unwrap().is_none());
+
+        assert!(quiz.check_turn(&alice.id).is_ok());
+        assert!(quiz.check_turn(&bob.id).is_err());
+        assert_eq!(quiz.end_turn().map(|turn| turn.player), Some(bob.id));
+        assert!(quiz.check_turn(&bob.id).is_ok());
+
+        // the turn passes to alice when bob gives up
+        assert_eq!(
+            quiz.accepts_give_up(&bob).unwrap(),
+            Response::message("player2 is removed.")
+        );
+        assert_eq!(quiz.turn().map(|turn| turn.player), Some(alice.id));
+
+        assert!(matches!(
+            quiz.accepts_give_up(&alice).unwrap(),
+            Response::Components(Component::Buttons { .. })
+        ));
+        assert!(quiz.is_empty());
+        assert_eq!(
+            quiz.take_events().last(),
+            Some(&Event::Revealed {
+                answer: "ab".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn moderator_kick_and_stop() {
+        let rules = Rules {
+            turn_based: true,
+            ..Rules::default()
+        };
+        let mut quiz = quiz("ab", rules);
+        let (alice, bob) = (player(1), player(2));
+        quiz.register(alice.id).unwrap();
+        quiz.register(bob.id).unwrap();
+        quiz.take_events();
+
+        // the turn of the kicked player passes to the next
+        assert_eq!(
+            quiz.kick(alice.id).unwrap(),
+            "<@1> is removed from the participants."
+        );
+        assert!(quiz.kick(alice.id).is_err());
+        assert_eq!(quiz.turn().map(|turn| turn.player), Some(bob.id));
+        quiz.kick(bob.id).unwrap();
+        assert!(quiz.is_empty());
+
+        assert!(quiz.stop().contains("`ab`"));
+        assert_eq!(
+            quiz.take_events(),
+            vec![
+                Event::Kicked { player: alice.id },
+                Event::TurnChanged { player: bob.id },
+                Event::Kicked { player: bob.id },
+                Event::Stopped {
+                    answer: "ab".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn query_budget() {
+        let rules = Rules {
+            query_budget: Some(2),
+            ..Rules::default()
+        };
+        let mut quiz = quiz("ab", rules);
+        let alice = player(1);
+
+        quiz.query(alice.id, "a").unwrap();
+        quiz.query(alice.id, "b").unwrap();
+        // asked queries are free, in whichever case
+        assert!(quiz.query(alice.id, "a").is_ok());
+        assert_eq!(
+            quiz.query(alice.id, "B").unwrap(),
+            Response::message("b => No")
+        );
+        assert_eq!(quiz.len(), 2);
+        assert!(matches!(
+            quiz.query(alice.id, "ab")
+                .unwrap_err()
+                .downcast_ref::<CommandError>(),
+            Some(CommandError::QueryBudget { budget: 2 })
+        ));
+    }
+
+    #[test]
+    fn quiz_suggestions() {
+        let mut quiz = quiz("(a|b)*a", Rules::default());
+        let alice = player(1);
+
+        quiz.query(alice.id, r#""""#).unwrap();
+        quiz.query(alice.id, "B").unwrap();
+        assert_eq!(quiz.unasked_words(alice.id, "", 4), ["a", "aa", "ab", "ba"]);
+        assert_eq!(quiz.unasked_words(alice.id, "b", 3), ["ba", "bb", "baa"]);
+        assert!(quiz.unasked_words(alice.id, "c", 3).is_empty());
+
+        quiz.inspect(&alice, "a*").unwrap();
+        quiz.inspect(&alice, "b*").unwrap();
+        quiz.inspect(&alice, "a*").unwrap();
+        assert!(quiz.inspect(&alice, "a|").is_err());
+        assert_eq!(quiz.recent_guesses(5), ["a*", "b*"]);
+    }
+
+    #[test]
+    fn private_queries() {
+        let rules = Rules {
+            private_queries: true,
+            ..Rules::default()
+        };
+        let mut quiz = quiz("(a|b)*a", rules);
+        let (alice, bob) = (player(1), player(2));
+        quiz.register(alice.id).unwrap();
+        quiz.register(bob.id).unwrap();
+        quiz.take_events();
+
+        quiz.query(alice.id, "ba").unwrap();
+        quiz.query(bob.id, "ab").unwrap();
+        assert_eq!(
+            quiz.get_query_history(alice.id),
+            Response::from(Embed::new(Colour::DarkBlue, "query history").field("ba", "Yes", true))
+        );
+        assert_eq!(
+            quiz.get_query_history(bob.id),
+            Response::from(Embed::new(Colour::DarkBlue, "query history").field("ab", "No", true))
+        );
+        assert_eq!(quiz.unasked_words(alice.id, "a", 2), ["a", "aa"]);
+        assert_eq!(
+            quiz.take_events()[0],
+            Event::Queried {
+                player: alice.id,
+                input: "ba".to_string(),
+                is_match: None,
+            }
+        );
+    }
+}
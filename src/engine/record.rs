@@ -0,0 +1,212 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use crate::regex::RegexAst;
+use anyhow::{anyhow, Context};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// A finished game, referred by [CustomId](crate::response::CustomId)s of its messages
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub id: u64,
+    #[serde(with = "answer")]
+    pub answer: RegexAst,
+    /// number of alphabets in the domain
+    pub size: u8,
+    /// seed of the generation, if generated
+    pub seed: Option<u64>,
+    pub queries: usize,
+    /// number of valid guesses
+    pub guesses: usize,
+    pub duration: Duration,
+    /// whether someone has got AC
+    pub solved: bool,
+}
+
+/// [RegexAst] in its textual form
+mod answer {
+    use crate::regex::RegexAst;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(answer: &RegexAst, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(answer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RegexAst, D::Error> {
+        let answer = String::deserialize(deserializer)?;
+        RegexAst::parse_str(&answer).map_err(D::Error::custom)
+    }
+}
+
+/// The latest finished games, optionally appended to a file of JSON lines
+/// so that the feedback on them is accepted after a restart
+pub struct RecordStore {
+    path: Option<PathBuf>,
+    /// number of the records kept
+    capacity: usize,
+    records: IndexMap<u64, GameRecord>,
+    /// number of the lines in the file, which is compacted when it doubles the capacity
+    lines: usize,
+}
+
+impl RecordStore {
+    /// A store only in memory
+    pub fn new(capacity: usize) -> Self {
+        RecordStore {
+            path: None,
+            capacity,
+            records: IndexMap::new(),
+            lines: 0,
+        }
+    }
+
+    /// Loads the records in `path`, which may not exist yet.
+    /// Broken lines are skipped.
+    pub fn open(path: impl AsRef<Path>, capacity: usize) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut store = RecordStore::new(capacity);
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                for record in text
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<GameRecord>(line).ok())
+                {
+                    store.keep(record);
+                }
+                store.lines = text.lines().count();
+            }
+            Err(why) if why.kind() == ErrorKind::NotFound => {}
+            Err(why) => return Err(why).with_context(|| anyhow!("fail to read {path:?}")),
+        }
+        store.path = Some(path);
+        if store.lines > store.capacity {
+            store.compact()?;
+        }
+        Ok(store)
+    }
+
+    /// Keeps `record`, forgetting the oldest one if the store is full.
+    pub fn insert(&mut self, record: GameRecord) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| anyhow!("fail to open {path:?}"))?;
+            writeln!(file, "{}", serde_json::to_string(&record)?)?;
+            self.lines += 1;
+        }
+        self.keep(record);
+        if self.lines >= self.capacity * 2 {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, id: u64) -> Option<&GameRecord> {
+        self.records.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    fn keep(&mut self, record: GameRecord) {
+        self.records.insert(record.id, record);
+        while self.records.len() > self.capacity {
+            self.records.shift_remove_index(0);
+        }
+    }
+
+    /// Rewrites the file with the records kept.
+    fn compact(&mut self) -> anyhow::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut text = String::new();
+        for record in self.records.values() {
+            text += &serde_json::to_string(record)?;
+            text.push('\n');
+        }
+        fs::write(path, text).with_context(|| anyhow!("fail to write {path:?}"))?;
+        self.lines = self.records.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameRecord, RecordStore};
+    use crate::regex::RegexAst;
+    use std::time::Duration;
+
+    fn record(id: u64, answer: &str) -> GameRecord {
+        GameRecord {
+            id,
+            answer: RegexAst::parse_str(answer).unwrap(),
+            size: 2,
+            seed: Some(id),
+            queries: 3,
+            guesses: 1,
+            duration: Duration::from_secs(42),
+            solved: true,
+        }
+    }
+
+    #[test]
+    fn records_survive_a_restart_within_the_capacity() {
+        let path =
+            std::env::temp_dir().join(format!("regexsoup-records-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = RecordStore::open(&path, 2).unwrap();
+        for (id, answer) in [(1, "a*"), (2, "(a|b)*a"), (3, "ab")] {
+            store.insert(record(id, answer)).unwrap();
+        }
+        assert_eq!(store.len(), 2);
+        assert!(store.get(1).is_none());
+
+        let reopened = RecordStore::open(&path, 2).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert!(reopened.get(1).is_none());
+        assert_eq!(reopened.get(2), Some(&record(2, "(a|b)*a")));
+        assert_eq!(reopened.get(3), Some(&record(3, "ab")));
+
+        // the file is compacted instead of growing forever
+        let mut store = reopened;
+        for id in 4..20 {
+            store.insert(record(id, "a*")).unwrap();
+        }
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines < 4, "{}", lines);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
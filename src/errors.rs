@@ -18,7 +18,7 @@
  */
 
 use crate::regex::Alphabet;
-use std::{collections::HashSet, fmt::Debug};
+use std::{collections::HashSet, fmt::Debug, path::PathBuf};
 use strum::IntoEnumIterator;
 use thiserror::Error;
 
@@ -53,4 +53,36 @@ Out of domain: {invalid:?}.
     },
     #[error("Time Limit Exceeded ({limit})")]
     Timeout { limit: String },
+    #[error("It's not your turn. Please wait for <@{current}>.")]
+    OutOfTurn { current: u64 },
+    #[error("The budget of {budget} queries has run out. Make a guess!")]
+    QueryBudget { budget: usize },
+    #[error("Mode `{mode}` is not allowed in this server. Allowed: {allowed}.")]
+    ModeNotAllowed { mode: String, allowed: String },
+    #[error("Option `{name}` is required.")]
+    MissingOption { name: String },
+    #[error("Option `{name}` must be {expected}.")]
+    InvalidOption { name: String, expected: String },
+}
+
+/// Errors in `regexsoup.toml`, reported at startup
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("fail to read the config {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("fail to parse the config {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("`{key}` is required. Set it in the config, or `{env}` in the environment.")]
+    Missing {
+        key: &'static str,
+        env: &'static str,
+    },
+    #[error("`{key}` {reason}.")]
+    Invalid { key: &'static str, reason: String },
 }
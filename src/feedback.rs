@@ -0,0 +1,321 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Good/Bad feedback on games, stored locally as JSON lines
+//!
+//! [FeedbackStats] works as a [ProblemScorer],
+//! so that the generator avoids the shapes of problems voted "Bad".
+
+use crate::{
+    engine::{GameRecord, PlayerId},
+    regex::{ProblemScorer, RegexAst},
+    response::{Colour, Embed, Vote},
+};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Votes needed before a shape is down-weighted
+const MINIMUM_VOTES: usize = 3;
+/// Lower bound of the score, so that no shape is banned forever
+const MINIMUM_SCORE: f64 = 0.1;
+/// Number of shapes shown in the stats
+const WORST_SHAPES: usize = 5;
+
+/// The structure of `ast` regardless of its alphabets, such as `(x|x)*x` for `(a|b)*a`.
+pub fn shape(ast: &RegexAst) -> String {
+    ast.simplify()
+        .to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphabetic() { 'x' } else { c })
+        .collect()
+}
+
+/// A vote on a finished game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Feedback {
+    pub game: u64,
+    pub regex: String,
+    pub shape: String,
+    pub size: u8,
+    pub seed: Option<u64>,
+    pub queries: usize,
+    pub guesses: usize,
+    pub duration_secs: u64,
+    pub voter: PlayerId,
+    pub vote: Vote,
+}
+
+impl Feedback {
+    pub fn new(record: &GameRecord, voter: PlayerId, vote: Vote) -> Self {
+        Feedback {
+            game: record.id,
+            regex: record.answer.to_string(),
+            shape: shape(&record.answer),
+            size: record.size,
+            seed: record.seed,
+            queries: record.queries,
+            guesses: record.guesses,
+            duration_secs: record.duration.as_secs(),
+            voter,
+            vote,
+        }
+    }
+}
+
+/// Feedback appended to a file of JSON lines
+pub struct FeedbackStore {
+    path: PathBuf,
+    entries: Vec<Feedback>,
+}
+
+impl FeedbackStore {
+    /// Loads the feedback in `path`, which may not exist yet.
+    /// Broken lines are skipped.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match fs::read_to_string(&path) {
+            Ok(text) => text
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(why) if why.kind() == ErrorKind::NotFound => vec![],
+            Err(why) => return Err(why).with_context(|| anyhow!("fail to read {path:?}")),
+        };
+        Ok(FeedbackStore { path, entries })
+    }
+
+    pub fn record(&mut self, feedback: Feedback) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| anyhow!("fail to open {:?}", self.path))?;
+        writeln!(file, "{}", serde_json::to_string(&feedback)?)?;
+        self.entries.push(feedback);
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[Feedback] {
+        &self.entries
+    }
+
+    pub fn stats(&self) -> FeedbackStats {
+        FeedbackStats::new(&self.entries)
+    }
+
+    /// Forgets all the feedback, and returns how many entries were forgotten.
+    /// The file is kept as `{path}.bak` in case of a mistake.
+    pub fn reset(&mut self) -> anyhow::Result<usize> {
+        let mut backup = self.path.clone().into_os_string();
+        backup.push(".bak");
+        match fs::rename(&self.path, &backup) {
+            Err(why) if why.kind() != ErrorKind::NotFound => {
+                return Err(why).with_context(|| anyhow!("fail to move {:?}", self.path));
+            }
+            _ => {}
+        }
+        Ok(std::mem::take(&mut self.entries).len())
+    }
+}
+
+/// Numbers of votes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub good: usize,
+    pub bad: usize,
+}
+
+impl Tally {
+    fn add(&mut self, vote: Vote) {
+        match vote {
+            Vote::Good => self.good += 1,
+            Vote::Bad => self.bad += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.good + self.bad
+    }
+
+    /// Laplace-smoothed rate of "Good"
+    pub fn good_rate(&self) -> f64 {
+        (self.good + 1) as f64 / (self.total() + 2) as f64
+    }
+}
+
+impl std::fmt::Display for Tally {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "👍 {} / 👎 {}", self.good, self.bad)
+    }
+}
+
+/// Summary of feedback, counting the last vote of each voter on each game
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackStats {
+    pub total: Tally,
+    pub by_size: BTreeMap<u8, Tally>,
+    pub by_shape: HashMap<String, Tally>,
+}
+
+impl FeedbackStats {
+    pub fn new(entries: &[Feedback]) -> Self {
+        let latest = entries
+            .iter()
+            .map(|feedback| ((feedback.game, feedback.voter), feedback))
+            .collect::<HashMap<_, _>>();
+        let mut stats = FeedbackStats::default();
+        for feedback in latest.values() {
+            stats.total.add(feedback.vote);
+            stats
+                .by_size
+                .entry(feedback.size)
+                .or_default()
+                .add(feedback.vote);
+            stats
+                .by_shape
+                .entry(feedback.shape.clone())
+                .or_default()
+                .add(feedback.vote);
+        }
+        stats
+    }
+
+    pub fn to_embed(&self) -> Embed {
+        let mut embed =
+            Embed::new(Colour::DarkGreen, "FEEDBACK STATS").field("total", self.total, false);
+        if self.total.total() == 0 {
+            return embed.field("Nothing to show", "-", false);
+        }
+        let by_size = self
+            .by_size
+            .iter()
+            .map(|(size, tally)| format!("size {size}: {tally}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        embed = embed.field("by size", by_size, false);
+
+        let mut shapes = self
+            .by_shape
+            .iter()
+            .filter(|(_, tally)| tally.total() >= MINIMUM_VOTES)
+            .collect::<Vec<_>>();
+        shapes.sort_by(|(_, a), (_, b)| a.good_rate().partial_cmp(&b.good_rate()).unwrap());
+        let worst = shapes
+            .iter()
+            .take(WORST_SHAPES)
+            .map(|(shape, tally)| format!("`{shape}`: {tally}"))
+            .collect::<Vec<_>>();
+        if !worst.is_empty() {
+            embed = embed.field("down-weighted shapes", worst.join("\n"), false);
+        }
+        embed
+    }
+}
+
+impl ProblemScorer for FeedbackStats {
+    fn score(&self, ast: &RegexAst) -> f64 {
+        match self.by_shape.get(&shape(ast)) {
+            Some(tally) if tally.total() >= MINIMUM_VOTES => tally.good_rate().max(MINIMUM_SCORE),
+            _ => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shape, Feedback, FeedbackStats, FeedbackStore};
+    use crate::{
+        engine::{GameRecord, PlayerId},
+        regex::{ProblemScorer, RegexAst},
+        response::Vote,
+    };
+    use std::time::Duration;
+
+    fn record(id: u64, answer: &str) -> GameRecord {
+        GameRecord {
+            id,
+            answer: RegexAst::parse_str(answer).unwrap(),
+            size: 2,
+            seed: Some(id),
+            queries: 3,
+            guesses: 1,
+            duration: Duration::from_secs(42),
+            solved: false,
+        }
+    }
+
+    #[test]
+    fn shapes_ignore_alphabets() {
+        assert_eq!(
+            shape(&RegexAst::parse_str("(a|b)*a").unwrap()),
+            shape(&RegexAst::parse_str("(b|a)*b").unwrap())
+        );
+    }
+
+    #[test]
+    fn bad_shapes_are_down_weighted() {
+        let entries = (0..4)
+            .map(|voter| Feedback::new(&record(1, "a*b"), PlayerId(voter), Vote::Bad))
+            .chain(std::iter::once(Feedback::new(
+                &record(2, "a|b"),
+                PlayerId(0),
+                Vote::Good,
+            )))
+            .collect::<Vec<_>>();
+        let stats = FeedbackStats::new(&entries);
+        assert_eq!(stats.total.bad, 4);
+        assert!(stats.score(&RegexAst::parse_str("b*a").unwrap()) < 0.5);
+        assert_eq!(stats.score(&RegexAst::parse_str("b|a").unwrap()), 1.0);
+    }
+
+    #[test]
+    fn feedback_is_persisted() {
+        let path = std::env::temp_dir().join(format!("feedback-{}.jsonl", rand::random::<u64>()));
+        let feedback = Feedback::new(&record(1, "a*b"), PlayerId(7), Vote::Good);
+
+        let mut store = FeedbackStore::open(&path).unwrap();
+        assert!(store.entries().is_empty());
+        store.record(feedback.clone()).unwrap();
+        // the last vote of the voter counts
+        store
+            .record(Feedback {
+                vote: Vote::Bad,
+                ..feedback.clone()
+            })
+            .unwrap();
+
+        let mut store = FeedbackStore::open(&path).unwrap();
+        assert_eq!(store.entries().len(), 2);
+        assert_eq!(store.entries()[0], feedback);
+        assert_eq!(store.stats().total.total(), 1);
+        assert_eq!(store.stats().total.bad, 1);
+
+        assert_eq!(store.reset().unwrap(), 2);
+        assert!(FeedbackStore::open(&path).unwrap().entries().is_empty());
+        let mut backup = path.into_os_string();
+        backup.push(".bak");
+        std::fs::remove_file(backup).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Health of the BOT for the Docker deployment
+//!
+//! A local HTTP server answers `/healthz` while the process is alive,
+//! `/readyz` once [Handler::ready] has fired and the gateway is connected,
+//! and `/metrics` of [METRICS].
+//!
+//! [Handler::ready]: serenity::client::EventHandler::ready
+
+use crate::metrics::METRICS;
+use axum::{http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Health of the process, updated by the events of the gateway
+pub static HEALTH: Lazy<Health> = Lazy::new(Health::default);
+
+#[derive(Default)]
+pub struct Health {
+    ready: AtomicBool,
+    connected: AtomicBool,
+    shutting_down: AtomicBool,
+}
+
+/// Body of `/readyz`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Readiness {
+    /// whether the `ready` event has been received
+    pub ready: bool,
+    /// whether the gateway is connected now
+    pub connected: bool,
+    pub shutting_down: bool,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.ready && self.connected && !self.shutting_down
+    }
+}
+
+impl Health {
+    /// Called on the `ready` event, which implies the connection.
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+        self.connected.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Relaxed);
+    }
+
+    pub fn set_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    pub fn readiness(&self) -> Readiness {
+        Readiness {
+            ready: self.ready.load(Ordering::Relaxed),
+            connected: self.connected.load(Ordering::Relaxed),
+            shutting_down: self.shutting_down.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+async fn readyz() -> impl IntoResponse {
+    let readiness = HEALTH.readiness();
+    let status = if readiness.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+/// `/healthz`, `/readyz` and `/metrics` with the number of the games running now
+pub fn router<F>(active_sessions: F) -> Router
+where
+    F: Fn() -> usize + Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route(
+            "/metrics",
+            get(move || {
+                let response = METRICS.respond(active_sessions());
+                async move { response }
+            }),
+        )
+}
+
+/// Serves `router` on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, router: Router) -> anyhow::Result<()> {
+    let server = axum::Server::try_bind(&addr)?.serve(router.into_make_service());
+    tracing::info!("serving the status on http://{}", server.local_addr());
+    server.await?;
+    Ok(())
+}
+
+/// Completes on SIGTERM, sent by `docker stop`, or on Ctrl-C.
+pub async fn terminated() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = sigterm.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(why) => tracing::warn!("fail to listen to SIGTERM: {why}"),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{router, Health};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    #[test]
+    fn ready_until_shutting_down() {
+        let health = Health::default();
+        assert!(!health.readiness().is_ready());
+        health.set_ready();
+        assert!(health.readiness().is_ready());
+        health.set_connected(false);
+        assert!(!health.readiness().is_ready());
+        health.set_connected(true);
+        health.set_shutting_down();
+        assert!(!health.readiness().is_ready());
+    }
+
+    #[tokio::test]
+    async fn endpoints_answer() {
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(router(|| 2).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        for (path, expected) in vec![
+            ("/healthz", "200 OK"),
+            ("/readyz", "503 Service Unavailable"),
+            ("/metrics", "200 OK"),
+        ] {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request =
+                format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(
+                response.starts_with(&format!("HTTP/1.1 {expected}")),
+                "{}",
+                response
+            );
+        }
+    }
+}
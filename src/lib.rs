@@ -41,8 +41,18 @@ pub mod bot;
 pub mod command_ext;
 pub mod commands;
 pub mod concepts;
+pub mod config;
+pub mod engine;
 pub mod errors;
+pub mod feedback;
+pub mod health;
+pub mod logging;
+pub mod metrics;
 pub mod notification;
+pub mod options;
 pub mod parser;
 pub mod regex;
+pub mod registry;
 pub mod response;
+pub mod serve;
+pub mod settings;
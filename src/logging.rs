@@ -0,0 +1,133 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Structured logs of the BOT and the HTTP API with [tracing]
+//!
+//! The logs are written to the standard output,
+//! and to rotating files if `logging.directory` is set.
+//! `RUST_LOG` takes precedence over `logging.level` for finer filters.
+//! The answers of the games are redacted unless `logging.redact_answers` is off.
+
+use crate::{
+    config::{LogFormat, LogRotation, LoggingConfig},
+    engine::Event,
+};
+use anyhow::anyhow;
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{
+    fmt::{self, MakeWriter},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+/// Shown instead of the answers
+pub const REDACTED: &str = "[redacted]";
+
+static REDACT_ANSWERS: AtomicBool = AtomicBool::new(true);
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Installs the global subscriber.
+/// The returned guard flushes the log files when dropped, so keep it until the end of `main`.
+pub fn init(config: &LoggingConfig) -> anyhow::Result<Option<WorkerGuard>> {
+    REDACT_ANSWERS.store(config.redact_answers, Ordering::Relaxed);
+
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,regexsoup={}", config.level)));
+    let mut layers = vec![layer(config.format, std::io::stdout, true)];
+    let guard = config.directory.as_ref().map(|directory| {
+        let rotation = match config.rotation {
+            LogRotation::Minutely => rolling::Rotation::MINUTELY,
+            LogRotation::Hourly => rolling::Rotation::HOURLY,
+            LogRotation::Daily => rolling::Rotation::DAILY,
+            LogRotation::Never => rolling::Rotation::NEVER,
+        };
+        let appender = rolling::RollingFileAppender::new(rotation, directory, "regexsoup.log");
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        layers.push(layer(config.format, writer, false));
+        guard
+    });
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()
+        .map_err(|why| anyhow!("fail to initialize the logs: {why}"))?;
+    Ok(guard)
+}
+
+/// `answer` as it should appear in the logs
+pub fn redact(answer: impl Display) -> String {
+    if REDACT_ANSWERS.load(Ordering::Relaxed) {
+        REDACTED.to_string()
+    } else {
+        answer.to_string()
+    }
+}
+
+/// `event` as it should appear in the logs, whose answer is redacted.
+pub fn redact_event(mut event: Event) -> Event {
+    if let Event::Solved { answer, .. }
+    | Event::Revealed { answer }
+    | Event::Expired { answer }
+    | Event::Stopped { answer } = &mut event
+    {
+        *answer = redact(&*answer);
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{redact_event, REDACTED};
+    use crate::engine::{Event, PlayerId};
+
+    #[test]
+    fn answers_are_redacted_by_default() {
+        assert_eq!(
+            redact_event(Event::Solved {
+                player: PlayerId(1),
+                answer: "(a|b)*a".to_string(),
+            }),
+            Event::Solved {
+                player: PlayerId(1),
+                answer: REDACTED.to_string(),
+            }
+        );
+        let joined = Event::Joined {
+            player: PlayerId(1),
+        };
+        assert_eq!(redact_event(joined.clone()), joined);
+    }
+}
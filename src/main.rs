@@ -24,489 +24,1553 @@
 #![feature(bool_to_option)]
 
 use anyhow::{anyhow, Context};
-use counted_array::counted_array;
-
-use itertools::Either;
-use once_cell::sync::Lazy;
+use indoc::indoc;
+use once_cell::sync::{Lazy, OnceCell};
 use regexsoup::{
-    bot::{Container, InspectionAcceptance, Msg, Quiz, Tsx},
-    command_ext::CommandExt,
-    commands,
+    bot::{Quiz, Rules, Turn},
+    command_ext::{Attachment, CommandExt},
+    command_options,
+    commands::{self, StartPlan, Suggestion},
     concepts::SameAs,
-    notification::{Notification, SlashCommand, To},
-    parser::{ComponentParser, CustomId},
-    regex::Alphabet,
+    config::Config,
+    engine::{Actors, GameRecord, Player, RecordStore, RECORDS_KEPT},
+    errors::CommandError,
+    feedback::{Feedback, FeedbackStore},
+    health::{self, HEALTH},
+    logging,
+    options::CommandOptions,
+    parser::{ComponentParser, CustomId, Menu},
+    regex::{ExportFormat, Neutral, ProblemScorer, RegexAst},
+    registry::{
+        AutocompleteHandler, Choice, CommandSpec, Dictionary, HandlerFuture, OptionKind,
+        OptionSpec, Registry,
+    },
+    response::{Colour, Component, Embed},
+    settings::{AllowedModes, GuildSettings, Language, SettingsStore},
 };
 use serenity::{
     async_trait,
-    builder::CreateEmbed,
-    client::{Client, EventHandler},
+    client::{bridge::gateway::event::ShardStageUpdateEvent, Client, EventHandler},
+    gateway::ConnectionStage,
+    http::Http,
     model::{
+        channel::Message,
+        event::ResumedEvent,
         gateway::Ready,
-        id::{ChannelId, UserId},
-        interactions::{application_command::ApplicationCommand, Interaction},
+        guild::{Member, Role},
+        id::{ChannelId, GuildId, UserId},
+        interactions::{
+            application_command::{ApplicationCommand, ApplicationCommandInteraction},
+            autocomplete::AutocompleteInteraction,
+            message_component::MessageComponentInteraction,
+            Interaction,
+        },
+        user::User,
     },
-    utils::Colour,
 };
 use std::{
-    collections::{HashMap, HashSet},
-    convert::TryInto,
     fmt::{Debug, Display},
     num::NonZeroU8,
     sync::{Arc, Mutex},
+    time::Instant,
 };
-use strum::IntoEnumIterator;
-use tokio::sync::mpsc::channel;
-
-counted_array!(
-    const COMMANDS: [&'static str; _] = [
-        "start",
-        "query",
-        "guess",
-        "summary",
-        "join",
-        "give-up",
-        "help",
-    ]
-);
-
-pub static CONTAINER: Lazy<Arc<Mutex<Container>>> = Lazy::new(|| {
-    let container = Container::default();
-    Arc::new(Mutex::new(container))
+use tracing::Instrument;
+
+/// Games of the channels, each run by its own task,
+/// with the latest finished games stored at `storage.records` of the config
+pub static GAMES: Lazy<Actors> = Lazy::new(|| {
+    let path = &config().storage.records;
+    let records = RecordStore::open(path, RECORDS_KEPT).expect("fail to open the records of games");
+    Actors::with_records(records)
+});
+
+/// Configuration loaded at startup
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+fn config() -> &'static Config {
+    CONFIG.get().expect("the config is loaded at startup")
+}
+
+/// Good/Bad feedback, stored at `storage.feedback` of the config
+static FEEDBACK: Lazy<Mutex<FeedbackStore>> = Lazy::new(|| {
+    let path = &config().storage.feedback;
+    Mutex::new(FeedbackStore::open(path).expect("fail to open the feedback store"))
+});
+
+/// Settings of the guilds by `/config`, stored at `storage.guilds` of the config
+static SETTINGS: Lazy<Mutex<SettingsStore>> = Lazy::new(|| {
+    let path = &config().storage.guilds;
+    Mutex::new(SettingsStore::open(path).expect("fail to open the settings of the guilds"))
 });
 
+/// Settings of `guild`, or the default in DMs
+fn settings_of(guild: Option<GuildId>) -> GuildSettings {
+    guild.map_or_else(GuildSettings::default, |guild| {
+        SETTINGS.lock().unwrap().get(guild.into())
+    })
+}
+
+fn is_admin(member: Option<&Member>) -> bool {
+    member
+        .and_then(|member| member.permissions)
+        .map_or(false, |permissions| permissions.administrator())
+}
+
+/// Whether `member` is an administrator or has the moderator role of the guild
+fn is_moderator(member: Option<&Member>, settings: &GuildSettings) -> bool {
+    is_admin(member)
+        || member
+            .zip(settings.moderator_role)
+            .map_or(false, |(member, role)| {
+                member.roles.iter().any(|id| id.0 == role)
+            })
+}
+
+/// Rejects `command` unless the user is a moderator, and returns whether it is allowed.
+async fn check_moderator(http: &Http, command: &ApplicationCommandInteraction) -> bool {
+    let settings = settings_of(command.guild_id);
+    if is_moderator(command.member.as_ref(), &settings) {
+        return true;
+    }
+    let _ = command
+        .ephemeral(
+            http,
+            settings
+                .language
+                .pick("モデレーターのみ使用できます", "Only for moderators."),
+        )
+        .await
+        .with_context(|| anyhow!("ERROR: fail to interaction"))
+        .logging_with(|_| "rejected a moderation command.");
+    false
+}
+
+/// Rejects `command` unless the user is an owner of the BOT, and returns whether it is allowed.
+async fn check_owner(http: &Http, command: &ApplicationCommandInteraction) -> bool {
+    if config().discord.is_owner(command.user.id.0) {
+        return true;
+    }
+    let _ = command
+        .ephemeral(
+            http,
+            settings_of(command.guild_id).language.pick(
+                "BOTの管理者のみ使用できます",
+                "Only for the owners of the BOT.",
+            ),
+        )
+        .await
+        .with_context(|| anyhow!("ERROR: fail to interaction"))
+        .logging_with(|_| "rejected an owner command.");
+    false
+}
+
+/// Runs `cmd` on `quiz`, and logs the events occurred meanwhile.
+fn with_events<F, R>(quiz: &mut Quiz, cmd: F) -> R
+where
+    F: FnOnce(&mut Quiz) -> R,
+{
+    let res = cmd(quiz);
+    for event in quiz.take_events() {
+        tracing::info!(event = ?logging::redact_event(event), "event");
+    }
+    res
+}
+
+fn no_game(language: Language) -> anyhow::Error {
+    anyhow!(language.pick(
+        "ゲームが開始していません",
+        "No game is running in this channel."
+    ))
+}
+
 #[async_trait]
+/// Games of Discord channels, whose errors are told in `language` of the server
 trait Containerized {
-    async fn command<F, R>(&self, channel: ChannelId, cmd: F) -> anyhow::Result<R>
+    async fn command<F, R>(
+        &self,
+        channel: ChannelId,
+        language: Language,
+        cmd: F,
+    ) -> anyhow::Result<R>
     where
-        F: FnOnce(&mut Quiz) -> R + Send + Sync + 'async_trait;
+        F: FnOnce(&mut Quiz) -> R + Send + 'static,
+        R: Send + 'static;
     async fn checked_command<F, R>(
         &self,
         channel: ChannelId,
+        language: Language,
         user: UserId,
         cmd: F,
     ) -> anyhow::Result<R>
     where
-        F: FnOnce(&mut Quiz) -> R + Send + Sync + 'async_trait;
-    async fn fresh(&self, channel: ChannelId, difficulty: NonZeroU8)
-        -> anyhow::Result<CreateEmbed>;
+        F: FnOnce(&mut Quiz) -> R + Send + 'static,
+        R: Send + 'static;
+    async fn fresh(
+        &self,
+        channel: ChannelId,
+        difficulty: NonZeroU8,
+        rules: Rules,
+    ) -> anyhow::Result<Embed>;
     async fn delete(&self, channel: ChannelId);
+    async fn stop(&self, channel: ChannelId, language: Language) -> anyhow::Result<String>;
+    async fn finished(&self, channel: ChannelId, language: Language) -> anyhow::Result<RegexAst>;
+    async fn record(&self, game: u64) -> Option<GameRecord>;
 }
 
 #[async_trait]
-impl Containerized for Lazy<Arc<Mutex<Container>>> {
-    async fn command<F, R>(&self, channel: ChannelId, cmd: F) -> anyhow::Result<R>
+impl Containerized for Lazy<Actors> {
+    async fn command<F, R>(
+        &self,
+        channel: ChannelId,
+        language: Language,
+        cmd: F,
+    ) -> anyhow::Result<R>
     where
-        F: FnOnce(&mut Quiz) -> R + Send + Sync + 'async_trait,
+        F: FnOnce(&mut Quiz) -> R + Send + 'static,
+        R: Send + 'static,
     {
-        loop {
-            if let Ok(mut lock) = self.try_lock() {
-                return lock
-                    .channel_map
-                    .get_mut(&channel)
-                    .ok_or_else(|| anyhow!("ゲームが開始していません"))?
-                    .as_mut()
-                    .map(cmd)
-                    .ok_or_else(|| anyhow!("not started"));
-            }
-        }
+        (**self)
+            .command(&channel.into(), |quiz| with_events(quiz, cmd))
+            .await
+            .ok_or_else(|| no_game(language))
     }
 
     async fn checked_command<F, R>(
         &self,
         channel: ChannelId,
+        language: Language,
         user: UserId,
         cmd: F,
     ) -> anyhow::Result<R>
     where
-        F: FnOnce(&mut Quiz) -> R + Send + Sync + 'async_trait,
+        F: FnOnce(&mut Quiz) -> R + Send + 'static,
+        R: Send + 'static,
     {
-        loop {
-            if let Ok(mut lock) = self.try_lock() {
-                return lock
-                    .channel_map
-                    .get_mut(&channel)
-                    .ok_or_else(|| anyhow!("ゲームが開始していません"))?
-                    .as_mut()
-                    .ok_or_else(|| anyhow!("ゲームが開始していません"))
-                    .and_then(|quiz: &mut Quiz| {
-                        quiz.is_participant(&user).then_some(quiz).ok_or_else(|| {
-                            anyhow!("まずは`start`コマンドでゲームを開始してください")
-                        })
-                    })
-                    .map(cmd);
-            }
-        }
+        Containerized::command(self, channel, language, move |quiz| {
+            quiz.is_participant(&user.into())
+                .then(|| cmd(quiz))
+                .ok_or_else(|| {
+                    anyhow!(language.pick(
+                        "まずは`start`コマンドでゲームを開始してください",
+                        "Start a game with the `start` command first."
+                    ))
+                })
+        })
+        .await
+        .flatten()
     }
 
     async fn fresh(
         &self,
         channel: ChannelId,
         difficulty: NonZeroU8,
-    ) -> anyhow::Result<CreateEmbed> {
-        let quiz = commands::generate_regex(difficulty).await?;
-
-        loop {
-            if let Ok(mut lock) = self.try_lock() {
-                let domain = Alphabet::iter()
-                    .take(difficulty.get().into())
-                    .collect::<HashSet<_>>();
-
-                let mut embed = CreateEmbed::default();
-                embed
-                    .colour(Colour::BLITZ_BLUE)
-                    .title("Starts a fresh REGEX-SOUP")
-                    .field("domain", format!("Σ = {domain:?}"), false);
-
-                return Ok(lock
-                    .channel_map
-                    .insert(channel, Some(quiz))
-                    .map(|_| embed.clone())
-                    .unwrap_or_else(move || {
-                        embed.field("ATTENTION:", "An old REGEX-SOUP is expired.", false);
-                        embed
-                    }));
-            }
-        }
+        rules: Rules,
+    ) -> anyhow::Result<Embed> {
+        let config = config();
+        let scorer: Arc<dyn ProblemScorer + Send + Sync> = if config.features.feedback {
+            Arc::new(FEEDBACK.lock().unwrap().stats())
+        } else {
+            Arc::new(Neutral)
+        };
+        let quiz = commands::generate_regex(
+            difficulty,
+            rules,
+            config.generation.params(),
+            scorer,
+            config.timeouts.generation(),
+        )
+        .await?;
+        let embed = quiz.introduction();
+
+        Ok(if self.start(channel.into(), quiz).await {
+            embed.field("ATTENTION:", "An old REGEX-SOUP is expired.", false)
+        } else {
+            embed
+        })
     }
 
     async fn delete(&self, channel: ChannelId) {
-        loop {
-            if let Ok(mut lock) = self.try_lock() {
-                lock.channel_map
-                    .entry(channel)
-                    .and_modify(|quiz| *quiz = None);
-                break;
-            }
-        }
+        self.finish(&channel.into()).await;
+    }
+
+    /// Takes the game at once, so that no command runs between stopping and closing it.
+    async fn stop(&self, channel: ChannelId, language: Language) -> anyhow::Result<String> {
+        let mut quiz = self
+            .finish(&channel.into())
+            .await
+            .ok_or_else(|| no_game(language))?;
+        Ok(with_events(&mut quiz, Quiz::stop))
+    }
+
+    async fn finished(&self, channel: ChannelId, language: Language) -> anyhow::Result<RegexAst> {
+        (**self).finished(&channel.into()).ok_or_else(|| {
+            anyhow!(language.pick(
+                "このチャンネルで終了したゲームはありません",
+                "No game has finished in this channel."
+            ))
+        })
+    }
+
+    async fn record(&self, game: u64) -> Option<GameRecord> {
+        (**self).record(game)
     }
 }
 
-#[async_trait]
+/// Logs the result of an interaction in the span of the interaction.
 pub trait Logger<T: Debug> {
-    async fn logging(self) -> anyhow::Result<(), !>
+    fn logging(self) -> anyhow::Result<(), !>
     where
         Self: SameAs<anyhow::Result<T>>;
 
-    async fn logging_with<F: Send + Sync + 'static, Log: Display>(
-        self,
-        f: F,
-    ) -> anyhow::Result<(), !>
+    fn logging_with<F, Log: Display>(self, f: F) -> anyhow::Result<(), !>
     where
         Self: SameAs<anyhow::Result<T>>,
         F: FnOnce(T) -> Log;
 }
 
-#[async_trait]
-impl<T: Debug + Send + Sync + 'static> Logger<T> for anyhow::Result<T> {
-    async fn logging(self) -> anyhow::Result<(), !>
+impl<T: Debug> Logger<T> for anyhow::Result<T> {
+    fn logging(self) -> anyhow::Result<(), !>
     where
         Self: SameAs<anyhow::Result<T>>,
     {
-        let tx = CENTRAL.sender();
-        tokio::task::spawn(async move {
-            match self {
-                Ok(msg) => {
-                    let _ = tx.send(Msg::Ok(format!("{msg:?}"))).await;
-                }
-                Err(err) => {
-                    let _ = tx.send(Msg::Err(err)).await;
-                }
-            }
-        });
+        match self {
+            Ok(value) => tracing::debug!("{value:?}"),
+            Err(why) => tracing::error!("{why:#}"),
+        }
         Ok(())
     }
 
-    async fn logging_with<F: Send + Sync + 'static, Log: Display>(
-        self,
-        f: F,
-    ) -> anyhow::Result<(), !>
+    fn logging_with<F, Log: Display>(self, f: F) -> anyhow::Result<(), !>
     where
         Self: SameAs<anyhow::Result<T>>,
         F: FnOnce(T) -> Log,
     {
-        let tx = CENTRAL.sender();
-        tokio::task::spawn(async move {
-            match self {
-                Ok(value) => {
-                    let _ = tx.send(Msg::Ok(format!("{}", f(value)))).await;
-                }
-                Err(err) => {
-                    let _ = tx.send(Msg::Err(err)).await;
-                }
-            }
-        });
+        match self {
+            Ok(value) => tracing::info!("{}", f(value)),
+            Err(why) => tracing::error!("{why:#}"),
+        }
         Ok(())
     }
 }
 
 trait AsEmbed {
-    fn as_embed(&self) -> CreateEmbed;
+    fn as_embed(&self) -> Embed;
 }
 
 impl AsEmbed for anyhow::Error {
-    fn as_embed(&self) -> CreateEmbed {
-        let mut embed = CreateEmbed::default();
-        embed
-            .colour(Colour::RED)
-            .title("ERROR")
-            .field("description:", format!("{self:#?}"), false);
-        embed
+    fn as_embed(&self) -> Embed {
+        Embed::new(Colour::Red, "ERROR").field("description:", format!("{self:#?}"), false)
     }
 }
 
-/// Handler for the BOT
-#[derive(Debug)]
-struct Handler;
+fn is_out_of_turn(why: &anyhow::Error) -> bool {
+    matches!(
+        why.downcast_ref::<CommandError>(),
+        Some(CommandError::OutOfTurn { .. })
+    )
+}
 
-#[async_trait]
-impl EventHandler for Handler {
-    async fn ready(&self, ctx: serenity::client::Context, _ready: Ready) {
-        let commands = commands::create_slash_commands(&ctx.http).await;
-        if let Ok(command) = commands {
-            for cmd in command.iter().filter(|cmd| {
-                !COMMANDS
-                    .iter()
-                    .any(|expected| cmd.name.starts_with(expected))
-            }) {
-                let _ =
-                    ApplicationCommand::delete_global_application_command(&ctx.http, cmd.id).await;
+async fn announce_turn(http: &Http, channel: ChannelId, turn: Turn) {
+    let _ = channel
+        .say(
+            http,
+            format!(
+                "It's <@{}>'s turn. ({}s)",
+                turn.player.0,
+                turn.limit.as_secs()
+            ),
+        )
+        .await
+        .with_context(|| anyhow!("ERROR: fail to announce the turn"))
+        .logging_with(|_| "successfully announced the turn.");
+}
+
+/// Skips the turns running out of time until the game `id` in `channel` finishes.
+async fn watch_turns(http: Arc<Http>, channel: ChannelId, id: u64) {
+    // the errors are not shown to anyone
+    let language = Language::default();
+    loop {
+        let turn = GAMES
+            .command(channel, language, move |quiz| {
+                (quiz.id() == id).then(|| (quiz.turn(), quiz.rules().turn_time_limit))
+            })
+            .await;
+        let deadline = match turn {
+            Ok(Some((Some(turn), _))) => turn.deadline,
+            // nobody has joined yet
+            Ok(Some((None, limit))) => Instant::now() + limit,
+            // the game has finished or has been replaced
+            _ => break,
+        };
+        tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)).await;
+
+        let skipped = GAMES
+            .command(channel, language, move |quiz| {
+                (quiz.id() == id).then(|| quiz.skip_expired_turn())
+            })
+            .await;
+        match skipped {
+            Ok(Some(Some((expired, turn)))) => {
+                let _ = channel
+                    .say(&http, format!("<@{}> ran out of time.", expired.player.0))
+                    .await;
+                announce_turn(&http, channel, turn).await;
             }
+            Ok(Some(None)) => {}
+            _ => break,
         }
-        println!("successfully connected!!");
-        let commands = ApplicationCommand::get_global_application_commands(&ctx.http).await;
-        println!("I now have the following global slash commands: {commands:#?}");
     }
+}
 
-    async fn interaction_create(&self, ctx: serenity::client::Context, interaction: Interaction) {
-        use regexsoup::parser::CommandParser;
+/// Closes idle games periodically, revealing their answers.
+async fn sweep(http: Arc<Http>) {
+    let timeouts = &config().timeouts;
+    let mut interval = tokio::time::interval(timeouts.sweep());
+    loop {
+        interval.tick().await;
+        let reaped = GAMES.reap(timeouts.idle()).await;
+        if reaped.is_empty() {
+            continue;
+        }
+        for (channel, mut quiz) in reaped {
+            let announcement = with_events(&mut quiz, Quiz::expire);
+            let _ = ChannelId(channel.0)
+                .say(&http, announcement)
+                .await
+                .with_context(|| anyhow!("ERROR: fail to announce the expiration"))
+                .logging_with(move |_| format!("closed the idle game in {channel}."));
+        }
+        tracing::info!(total = GAMES.reaped(), "reaped idle games");
+    }
+}
 
-        if let Some(command) = interaction.clone().application_command() {
-            let flat_data = command.data.parse().unwrap();
-            let (head, tail) = flat_data.split_first().unwrap();
-            let dictionary = tail.iter().cloned().collect::<HashMap<_, _>>();
-
-            match head {
-                (_, Notification::SlashCommand(SlashCommand::Command(cmd))) if cmd.eq("start") => {
-                    println!("cmd: start");
-                    let difficulty: NonZeroU8 = (dictionary
-                        .get("size")
-                        .map_or_else(|| Ok(3i64), |size| size.to::<i64>())
-                        .unwrap() as u8)
-                        .try_into()
-                        .unwrap();
-                    let res = CONTAINER.fresh(command.channel_id, difficulty).await;
-                    let _ = command
-                        .embed(&ctx.http, res.unwrap_or_else(|why| why.as_embed()))
-                        .await
-                        .with_context(|| anyhow!("ERROR: fail to interaction"))
-                        .logging_with(|_| {
-                            "parse error: successfully finished to send error message."
-                        })
-                        .await;
-                }
-                (_, Notification::SlashCommand(SlashCommand::Command(cmd))) if cmd.eq("query") => {
-                    println!("cmd: query");
-                    tokio::task::spawn(async move {
-                        let input = dictionary.get("input").unwrap().to::<String>().unwrap();
-                        let is_match = CONTAINER
-                            .checked_command(command.channel_id, command.user.id, |quiz| {
-                                quiz.query(&input)
-                            })
-                            .await
-                            .flatten();
-
-                        match is_match {
-                            Ok(is_match) => {
-                                let _ = command
-                                    .message(&ctx.http, is_match)
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(|_| "successfully finished query command.")
-                                    .await;
-                            }
-                            Err(why) => {
-                                let _ = command
-                                    .embed(&ctx.http, why.as_embed())
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(move |_| format!("{why:#?}"))
-                                    .await;
-                            }
-                        }
-                    });
+/// Closes the running games before the process exits, revealing their answers.
+/// The feedback and the settings are already saved on every change.
+async fn shut_down(http: &Http) {
+    for (channel, mut quiz) in GAMES.close_all().await {
+        let announcement = with_events(&mut quiz, Quiz::shut_down);
+        let _ = ChannelId(channel.0)
+            .say(http, announcement)
+            .await
+            .with_context(|| anyhow!("ERROR: fail to announce the shutdown"))
+            .logging_with(move |_| format!("closed the game in {channel}."));
+    }
+}
+
+/// Starts a fresh game, and watches the turns if the game is turn-based.
+async fn start_game(
+    http: &Arc<Http>,
+    channel: ChannelId,
+    settings: &GuildSettings,
+    difficulty: NonZeroU8,
+    rules: Rules,
+) -> anyhow::Result<Embed> {
+    let embed = GAMES.fresh(channel, difficulty, rules).await?;
+    if rules.turn_based {
+        let id = GAMES
+            .command(channel, settings.language, |quiz| quiz.id())
+            .await?;
+        tokio::task::spawn(watch_turns(Arc::clone(http), channel, id));
+    }
+    Ok(embed)
+}
+
+/// Opens a thread from `message` for a game, or stays in `channel` if it fails.
+async fn open_thread(
+    http: &Http,
+    channel: ChannelId,
+    message: serenity::Result<Message>,
+) -> ChannelId {
+    let thread = match message {
+        Ok(message) => {
+            channel
+                .create_public_thread(http, message.id, |thread| thread.name("REGEX-SOUP"))
+                .await
+        }
+        Err(why) => Err(why),
+    };
+    match thread {
+        Ok(thread) => thread.id,
+        Err(why) => {
+            tracing::warn!("fail to open a thread: {why}");
+            channel
+        }
+    }
+}
+
+/// Adds `handler` to `option` if autocomplete is enabled in the config.
+fn with_autocomplete(option: OptionSpec, handler: AutocompleteHandler) -> OptionSpec {
+    if config().features.autocomplete {
+        option.autocomplete(handler)
+    } else {
+        option
+    }
+}
+
+/// All the slash commands of the BOT
+static REGISTRY: Lazy<Registry> = Lazy::new(|| {
+    let registry = Registry::new()
+        .command(
+            CommandSpec::new("start", "Starting new regex-soup", start)
+                .help(indoc! {r#"
+                    Starts a fresh REGEX-SOUP.
+                    `normal` mode: the first AC wins, `golf` mode: shorter ACs score higher
+                    without options: choose the difficulty and the mode from a menu,
+                    unless `/config` sets the size or allows only one mode
+                "#})
+                .option(
+                    OptionSpec::new(
+                        "size",
+                        "number of characters in the domain-set",
+                        OptionKind::Integer,
+                    )
+                    .choices((1..=10).map(Choice::Integer)),
+                )
+                .option(
+                    OptionSpec::new("mode", "rules of the game", OptionKind::String)
+                        .choices([Choice::String("normal"), Choice::String("golf")]),
+                )
+                .option(OptionSpec::new(
+                    "turns",
+                    "take turns in the order of `/join` (one query or guess per turn)",
+                    OptionKind::Boolean,
+                ))
+                .option(OptionSpec::new(
+                    "private",
+                    "results of `/query` are only visible to the querier",
+                    OptionKind::Boolean,
+                )),
+        )
+        .command(
+            CommandSpec::new(
+                "query",
+                "Query whether is matched with regular expression.",
+                query,
+            )
+            .option(with_autocomplete(
+                OptionSpec::new(
+                    "input",
+                    r#"alphabets to test (`""` is accepted as empty string)"#,
+                    OptionKind::String,
+                )
+                .required(),
+                suggest_queries,
+            )),
+        )
+        .command(
+            CommandSpec::new("guess", "Check your answer.", guess).option(with_autocomplete(
+                OptionSpec::new("regex", "regex you guess", OptionKind::String).required(),
+                suggest_guesses,
+            )),
+        )
+        .command(
+            CommandSpec::new("summary", "Dump the results of the query so far.", summary)
+                .help("Shows the history of queries."),
+        )
+        .command(
+            CommandSpec::new("join", "Register your participation.", join)
+                .help("You have to `/join` first to take part in the quiz!"),
+        )
+        .command(
+            CommandSpec::new("give-up", "Register your despair.", give_up).help(indoc! {r#"
+                When all participants have `give-up`,
+                the quiz will end and the answers will be revealed!
+            "#}),
+        )
+        .command(
+            CommandSpec::new(
+                "export",
+                "Export the answer of the last finished game as graphs.",
+                export,
+            )
+            .option(
+                OptionSpec::new("format", "format of graphs", OptionKind::String)
+                    .required()
+                    .choices([Choice::String("dot"), Choice::String("mermaid")]),
+            ),
+        );
+    let registry = registry
+        .command(
+            CommandSpec::new("stop", "Stop the game and reveal the answer.", stop)
+                .help("Force-ends the game in this channel. Only for moderators."),
+        )
+        .command(
+            CommandSpec::new("kick", "Remove a player from the game.", kick)
+                .help("Removes the player from the participants. Only for moderators.")
+                .option(OptionSpec::new("user", "player to remove", OptionKind::User).required()),
+        )
+        .command(
+            CommandSpec::new("reveal", "Show the answer only to you.", reveal)
+                .help("Shows the answer of the running game privately. Only for moderators."),
+        );
+    let registry = if config().features.feedback {
+        registry
+            .command(
+                CommandSpec::new(
+                    "feedback-stats",
+                    "Summarize feedback on problems.",
+                    feedback_stats,
+                )
+                .help("Shows the Good/Bad votes on past problems. Only for the owners of the BOT."),
+            )
+            .command(
+                CommandSpec::new("reset-stats", "Forget all the feedback.", reset_stats).help(
+                    "Clears the Good/Bad votes on past problems. Only for the owners of the BOT.",
+                ),
+            )
+    } else {
+        registry
+    };
+    registry
+        .command(
+            CommandSpec::new("config", "Change the settings of this server.", configure)
+                .help(indoc! {r#"
+                    Changes the defaults of `/start` in this server. Only for administrators.
+                    without options: shows the current settings
+                "#})
+                .option(
+                    OptionSpec::new(
+                        "size",
+                        "number of characters when `size` is omitted",
+                        OptionKind::Integer,
+                    )
+                    .choices((1..=10).map(Choice::Integer)),
+                )
+                .option(
+                    OptionSpec::new(
+                        "language",
+                        "language of the rejections and the notices (games are in English)",
+                        OptionKind::String,
+                    )
+                    .choices([Choice::String("ja"), Choice::String("en")]),
+                )
+                .option(
+                    OptionSpec::new("modes", "modes allowed in `/start`", OptionKind::String)
+                        .choices([
+                            Choice::String("all"),
+                            Choice::String("normal"),
+                            Choice::String("golf"),
+                        ]),
+                )
+                .option(OptionSpec::new(
+                    "query_budget",
+                    "maximum number of queries in a game (0 for unlimited)",
+                    OptionKind::Integer,
+                ))
+                .option(OptionSpec::new(
+                    "time_limit",
+                    "seconds of each turn (0 for the default)",
+                    OptionKind::Integer,
+                ))
+                .option(OptionSpec::new(
+                    "thread",
+                    "play each game in a thread",
+                    OptionKind::Boolean,
+                ))
+                .option(OptionSpec::new(
+                    "moderator",
+                    "role allowed to `/stop`, `/kick` and `/reveal` (@everyone to unset)",
+                    OptionKind::Role,
+                )),
+        )
+        .command(CommandSpec::new("help", "helpful", help).help("Shows this help."))
+});
+
+command_options! {
+    struct ConfigOptions {
+        size: Option<NonZeroU8>,
+        language: Option<Language>,
+        modes: Option<AllowedModes>,
+        query_budget: Option<u64>,
+        time_limit: Option<u64>,
+        thread: Option<bool>,
+        moderator: Option<Role>,
+    }
+}
+
+command_options! {
+    struct KickOptions {
+        user: User,
+    }
+}
+
+command_options! {
+    struct QueryOptions {
+        input: String,
+    }
+}
+
+command_options! {
+    struct GuessOptions {
+        regex: String,
+    }
+}
+
+command_options! {
+    struct ExportOptions {
+        format: ExportFormat,
+    }
+}
+
+fn start(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    dictionary: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let settings = settings_of(command.guild_id);
+        let default_size = config().generation.default_size();
+        let game = match commands::plan_start(&dictionary, &settings, default_size) {
+            Ok(StartPlan::Menu(menu)) => {
+                tracing::debug!("cmd: start (picker)");
+                let _ = command
+                    .respond(&ctx.http, Component::SelectMenu(menu).into())
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully sent the start menu.");
+                return;
+            }
+            Ok(StartPlan::Game { size, rules }) => Ok((size, rules)),
+            Err(why) => Err(why),
+        };
+        tracing::debug!("cmd: start");
+        // the generation may take more than 3 seconds
+        let _ = command
+            .defer(&ctx.http)
+            .await
+            .with_context(|| anyhow!("ERROR: fail to defer"))
+            .logging_with(|_| "successfully deferred start command.");
+        let res = match game {
+            Ok((size, rules)) => {
+                let channel = if settings.thread {
+                    let message = command.get_interaction_response(&ctx.http).await;
+                    open_thread(&ctx.http, command.channel_id, message).await
+                } else {
+                    command.channel_id
+                };
+                start_game(&ctx.http, channel, &settings, size, rules).await
+            }
+            Err(why) => Err(why.into()),
+        };
+        let _ = command
+            .edit(&ctx.http, res.unwrap_or_else(|why| why.as_embed()).into())
+            .await
+            .with_context(|| anyhow!("ERROR: fail to interaction"))
+            .logging_with(|_| "successfully finished start command.");
+    })
+}
+
+fn query(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    dictionary: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: query");
+        let user = command.user.id.into();
+        let is_match = match QueryOptions::parse(&dictionary) {
+            Ok(QueryOptions { input }) => GAMES
+                .checked_command(
+                    command.channel_id,
+                    settings_of(command.guild_id).language,
+                    command.user.id,
+                    move |quiz| -> anyhow::Result<_> {
+                        quiz.check_turn(&user)?;
+                        let is_match = quiz.query(user, &input)?;
+                        Ok((is_match, quiz.end_turn(), quiz.rules().private_queries))
+                    },
+                )
+                .await
+                .flatten(),
+            Err(why) => Err(why.into()),
+        };
+
+        match is_match {
+            Ok((is_match, turn, private)) => {
+                let res = if private {
+                    command.respond_ephemeral(&ctx.http, is_match).await
+                } else {
+                    command.respond(&ctx.http, is_match).await
+                };
+                let _ = res
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished query command.");
+                if let Some(turn) = turn {
+                    announce_turn(&ctx.http, command.channel_id, turn).await;
                 }
-                (_, Notification::SlashCommand(SlashCommand::Command(cmd))) if cmd.eq("guess") => {
-                    println!("cmd: guess");
-                    tokio::task::spawn(async move {
-                        let input = dictionary.get("regex").unwrap().to::<String>().unwrap();
-
-                        let inspection = CONTAINER
-                            .checked_command(command.channel_id, command.user.id, |quiz| {
-                                quiz.inspect(&input)
-                            })
-                            .await
-                            .flatten();
-
-                        match inspection {
-                            Ok(res) => {
-                                if let InspectionAcceptance::Accepted(_) = res {
-                                    CONTAINER.delete(command.channel_id).await;
-                                }
-                                let _ = command
-                                    .message(&ctx.http, res)
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(|_| "successfully finished guess command.")
-                                    .await;
-                            }
-                            Err(why) => {
-                                let _ = command
-                                    .embed(&ctx.http, why.as_embed())
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(move |_| format!("{why:#?}"))
-                                    .await;
-                            }
-                        }
-                    });
+            }
+            Err(why) if is_out_of_turn(&why) => {
+                let _ = command
+                    .ephemeral(&ctx.http, format!("{why}"))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why}"));
+            }
+            Err(why) => {
+                let _ = command
+                    .embed(&ctx.http, why.as_embed())
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why:#?}"));
+            }
+        }
+    })
+}
+
+fn guess(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    dictionary: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: guess");
+        let regex = match GuessOptions::parse(&dictionary) {
+            Ok(GuessOptions { regex }) => regex,
+            Err(why) => {
+                let _ = command
+                    .embed(&ctx.http, anyhow::Error::from(why).as_embed())
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "parse error: successfully finished to send error message.");
+                return;
+            }
+        };
+
+        let player = Player::from(&command.user);
+        let user = player.id;
+        // checked before deferring, since a deferred response cannot turn into an ephemeral one
+        let checked = GAMES
+            .checked_command(
+                command.channel_id,
+                settings_of(command.guild_id).language,
+                command.user.id,
+                move |quiz| quiz.check_turn(&user),
+            )
+            .await
+            .and_then(|checked| Ok(checked?));
+        match checked {
+            Ok(()) => {}
+            Err(why) if is_out_of_turn(&why) => {
+                let _ = command
+                    .ephemeral(&ctx.http, format!("{why}"))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why}"));
+                return;
+            }
+            Err(why) => {
+                let _ = command
+                    .embed(&ctx.http, why.as_embed())
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why:#?}"));
+                return;
+            }
+        }
+
+        // the equivalence check may take more than 3 seconds
+        let _ = command
+            .defer(&ctx.http)
+            .await
+            .with_context(|| anyhow!("ERROR: fail to defer"))
+            .logging_with(|_| "successfully deferred guess command.");
+        let language = settings_of(command.guild_id).language;
+        let inspection = async {
+            let guess = GAMES
+                .checked_command(command.channel_id, language, command.user.id, move |quiz| {
+                    quiz.check_turn(&user)?;
+                    quiz.guess(&regex)
+                })
+                .await
+                .flatten()?;
+            // the equivalence check is CPU-bound, so it runs out of the game
+            let verdict = guess.judge_blocking().await?;
+            GAMES
+                .checked_command(
+                    command.channel_id,
+                    language,
+                    command.user.id,
+                    move |quiz| -> anyhow::Result<_> {
+                        quiz.check_turn(&user)?;
+                        let res = quiz.settle(&player, verdict)?;
+                        let solved = quiz.is_solved();
+                        let turn = if solved { None } else { quiz.end_turn() };
+                        Ok((res, solved, turn))
+                    },
+                )
+                .await
+                .flatten()
+        }
+        .await;
+
+        match inspection {
+            Ok((res, solved, turn)) => {
+                if solved {
+                    GAMES.delete(command.channel_id).await;
                 }
-                (_, Notification::SlashCommand(SlashCommand::Command(cmd)))
-                    if cmd.eq("summary") =>
-                {
-                    println!("cmd: summary");
-                    tokio::task::spawn(async move {
-                        let summary = CONTAINER
-                            .checked_command(command.channel_id, command.user.id, |quiz| {
-                                quiz.get_query_history()
-                            })
-                            .await;
-                        match summary {
-                            Ok(summary) => {
-                                let _ = command
-                                    .embed(&ctx.http, summary)
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(|_| "successfully finished summary command.")
-                                    .await;
-                            }
-                            Err(why) => {
-                                let _ = command
-                                    .message(&ctx.http, format!("{why}"))
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(move |_| format!("{why}"))
-                                    .await;
-                            }
-                        }
-                    });
+                let _ = command
+                    .edit(&ctx.http, res)
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished guess command.");
+                if let Some(turn) = turn {
+                    announce_turn(&ctx.http, command.channel_id, turn).await;
                 }
-                (_, Notification::SlashCommand(SlashCommand::Command(cmd))) if cmd.eq("join") => {
-                    println!("cmd: join");
-                    tokio::task::spawn(async move {
-                        let res = CONTAINER
-                            .checked_command(command.channel_id, command.user.id, |quiz| {
-                                quiz.register(command.user.id)
-                            })
-                            .await
-                            .flatten()
-                            .map(|_| format!("{} is added.", command.user.name));
-
-                        match res {
-                            Ok(msg) => {
-                                let _ = command
-                                    .message(&ctx.http, &msg)
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(|_| "successfully finished join command.")
-                                    .await;
-                            }
-                            Err(why) => {
-                                let _ = command
-                                    .message(&ctx.http, format!("{why}"))
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(move |_| format!("{why}"))
-                                    .await;
-                            }
-                        }
-                    });
+            }
+            Err(why) => {
+                let _ = command
+                    .edit(&ctx.http, why.as_embed().into())
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why:#?}"));
+            }
+        }
+    })
+}
+
+fn summary(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: summary");
+        let user = command.user.id.into();
+        let summary = GAMES
+            .checked_command(
+                command.channel_id,
+                settings_of(command.guild_id).language,
+                command.user.id,
+                move |quiz| (quiz.get_query_history(user), quiz.rules().private_queries),
+            )
+            .await;
+        match summary {
+            Ok((summary, private)) => {
+                let res = if private {
+                    command.respond_ephemeral(&ctx.http, summary).await
+                } else {
+                    command.respond(&ctx.http, summary).await
+                };
+                let _ = res
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished summary command.");
+            }
+            Err(why) => {
+                let _ = command
+                    .message(&ctx.http, format!("{why}"))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why}"));
+            }
+        }
+    })
+}
+
+fn join(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: join");
+        let user = command.user.id.into();
+        let res = GAMES
+            .command(
+                command.channel_id,
+                settings_of(command.guild_id).language,
+                move |quiz| quiz.register(user),
+            )
+            .await
+            .flatten()
+            .map(|turn| (format!("{} is added.", command.user.name), turn));
+
+        match res {
+            Ok((msg, turn)) => {
+                let _ = command
+                    .message(&ctx.http, &msg)
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished join command.");
+                if let Some(turn) = turn {
+                    announce_turn(&ctx.http, command.channel_id, turn).await;
                 }
-                (_, Notification::SlashCommand(SlashCommand::Command(cmd)))
-                    if cmd.eq("give-up") =>
-                {
-                    println!("cmd: give-up");
-                    tokio::task::spawn(async move {
-                        let res = CONTAINER
-                            .checked_command(command.channel_id, command.user.id, |quiz| {
-                                quiz.accepts_give_up(&command.user)
-                            })
-                            .await
-                            .flatten();
-
-                        match res {
-                            Ok(either) => match either {
-                                Either::Right((content, buttons)) => {
-                                    CONTAINER.delete(command.channel_id).await;
-                                    let _ = command
-                                        .button(&ctx.http, content, buttons)
-                                        .await
-                                        .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                        .logging_with(|_| "successfully finished give-up command.")
-                                        .await;
-                                }
-                                Either::Left(msg) => {
-                                    let _ = command
-                                        .message(&ctx.http, &msg)
-                                        .await
-                                        .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                        .logging_with(|_| "successfully finished give-up command.")
-                                        .await;
-                                }
+            }
+            Err(why) => {
+                let _ = command
+                    .message(&ctx.http, format!("{why}"))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why}"));
+            }
+        }
+    })
+}
+
+fn give_up(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: give-up");
+        let player = Player::from(&command.user);
+        let res = GAMES
+            .checked_command(
+                command.channel_id,
+                settings_of(command.guild_id).language,
+                command.user.id,
+                move |quiz| {
+                    let current = quiz.turn().map(|turn| turn.player);
+                    quiz.accepts_give_up(&player).map(|response| {
+                        // the turn passes to the next player if the current player gives up
+                        let turn = quiz.turn().filter(|turn| Some(turn.player) != current);
+                        (response, quiz.is_empty(), quiz.get_answer_regex(), turn)
+                    })
+                },
+            )
+            .await
+            .flatten();
+
+        match res {
+            Ok((response, true, answer, _)) => {
+                GAMES.delete(command.channel_id).await;
+                let _ = command
+                    .respond(&ctx.http, response)
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished give-up command.");
+                let _ = async {
+                    let data = answer.render_minimal_dfa_png()?;
+                    command
+                        .attachment(
+                            &ctx.http,
+                            "The minimal DFA of the answer:",
+                            Attachment {
+                                filename: "answer.png".to_string(),
+                                data,
                             },
-                            Err(why) => {
-                                let _ = command
-                                    .message(&ctx.http, format!("{why}"))
-                                    .await
-                                    .with_context(|| anyhow!("ERROR: fail to interaction"))
-                                    .logging_with(move |_| format!("{why}"))
-                                    .await;
-                            }
-                        }
-                    });
-                }
-                (_, Notification::SlashCommand(SlashCommand::Command(cmd))) if cmd.eq("help") => {
-                    let _ = command
-                        .embed(&ctx.http, commands::help())
+                        )
                         .await
-                        .with_context(|| anyhow!("ERROR: fail to interaction"))
-                        .logging_with(|_| "successfully finished help command.")
-                        .await;
                 }
-                (_, unknown) => {
-                    let _ = CENTRAL
-                        .sender()
-                        .send(Msg::Err(anyhow::anyhow!("unknown command: {:?}", unknown)))
-                        .await;
+                .await
+                .with_context(|| anyhow!("ERROR: fail to send the answer DFA"))
+                .logging_with(|_| "successfully sent the answer DFA.");
+            }
+            Ok((response, false, _, turn)) => {
+                let _ = command
+                    .respond(&ctx.http, response)
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished give-up command.");
+                if let Some(turn) = turn {
+                    announce_turn(&ctx.http, command.channel_id, turn).await;
+                }
+            }
+            Err(why) => {
+                let _ = command
+                    .message(&ctx.http, format!("{why}"))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why}"));
+            }
+        }
+    })
+}
+
+fn export(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    dictionary: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: export");
+        let res = match ExportOptions::parse(&dictionary) {
+            Ok(ExportOptions { format }) => GAMES
+                .finished(command.channel_id, settings_of(command.guild_id).language)
+                .await
+                .map(|answer| (format, answer)),
+            Err(why) => Err(why.into()),
+        };
+
+        match res {
+            Ok((format, answer)) => {
+                let _ = command
+                    .message(&ctx.http, format!("The answer was `{answer}`."))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished export command.");
+                let _ = command
+                    .attachment(
+                        &ctx.http,
+                        "Syntax tree, Thompson NFA and minimal DFA of the answer:",
+                        Attachment {
+                            filename: format!("answer.{}", format.extension()),
+                            data: answer.export_document(format).into_bytes(),
+                        },
+                    )
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to send the export"))
+                    .logging_with(|_| "successfully sent the export.");
+            }
+            Err(why) => {
+                let _ = command
+                    .message(&ctx.http, format!("{why}"))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why}"));
+            }
+        }
+    })
+}
+
+fn feedback_stats(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: feedback-stats");
+        if !check_owner(&ctx.http, &command).await {
+            return;
+        }
+
+        let stats = FEEDBACK.lock().unwrap().stats();
+        let _ = command
+            .respond_ephemeral(&ctx.http, stats.to_embed().into())
+            .await
+            .with_context(|| anyhow!("ERROR: fail to interaction"))
+            .logging_with(|_| "successfully finished feedback-stats command.");
+    })
+}
+
+fn configure(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    dictionary: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: config");
+        let guild = match command.guild_id {
+            Some(guild) => guild,
+            None => {
+                let _ = command
+                    .ephemeral(
+                        &ctx.http,
+                        settings_of(None)
+                            .language
+                            .pick("サーバー内でのみ使用できます", "Only in servers."),
+                    )
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "rejected config command in DM.");
+                return;
+            }
+        };
+        if !is_admin(command.member.as_ref()) {
+            let language = settings_of(Some(guild)).language;
+            let _ = command
+                .ephemeral(
+                    &ctx.http,
+                    language.pick("管理者のみ使用できます", "Only for administrators."),
+                )
+                .await
+                .with_context(|| anyhow!("ERROR: fail to interaction"))
+                .logging_with(|_| "rejected config command.");
+            return;
+        }
+
+        let res = ConfigOptions::parse(&dictionary)
+            .map_err(anyhow::Error::from)
+            .and_then(|options| {
+                SETTINGS.lock().unwrap().update(guild.into(), |settings| {
+                    if let Some(size) = options.size {
+                        settings.default_size = Some(size);
+                    }
+                    if let Some(language) = options.language {
+                        settings.language = language;
+                    }
+                    if let Some(AllowedModes(modes)) = options.modes {
+                        settings.allowed_modes = modes;
+                    }
+                    if let Some(budget) = options.query_budget {
+                        settings.query_budget = (budget > 0).then_some(budget as usize);
+                    }
+                    if let Some(secs) = options.time_limit {
+                        settings.turn_time_limit = (secs > 0).then_some(secs);
+                    }
+                    if let Some(thread) = options.thread {
+                        settings.thread = thread;
+                    }
+                    if let Some(role) = options.moderator {
+                        // the id of @everyone is the same as the guild
+                        settings.moderator_role = (role.id.0 != guild.0).then_some(role.id.0);
+                    }
+                })
+            })
+            .map(|settings| settings.to_embed(config().generation.default_size()));
+        let _ = command
+            .respond_ephemeral(&ctx.http, res.unwrap_or_else(|why| why.as_embed()).into())
+            .await
+            .with_context(|| anyhow!("ERROR: fail to interaction"))
+            .logging_with(move |_| format!("successfully finished config command in {guild}."));
+    })
+}
+
+fn stop(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: stop");
+        if !check_moderator(&ctx.http, &command).await {
+            return;
+        }
+        let res = GAMES
+            .stop(command.channel_id, settings_of(command.guild_id).language)
+            .await;
+        let _ = command
+            .message(&ctx.http, res.unwrap_or_else(|why| format!("{why}")))
+            .await
+            .with_context(|| anyhow!("ERROR: fail to interaction"))
+            .logging_with(|_| "successfully finished stop command.");
+    })
+}
+
+fn kick(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    dictionary: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: kick");
+        if !check_moderator(&ctx.http, &command).await {
+            return;
+        }
+        let res = match KickOptions::parse(&dictionary) {
+            Ok(KickOptions { user }) => {
+                let player = user.id.into();
+                GAMES
+                    .command(
+                        command.channel_id,
+                        settings_of(command.guild_id).language,
+                        move |quiz| {
+                            let current = quiz.turn().map(|turn| turn.player);
+                            quiz.kick(player).map(|msg| {
+                                // the turn passes to the next player if the current player is kicked
+                                let turn = quiz.turn().filter(|turn| Some(turn.player) != current);
+                                (msg, turn)
+                            })
+                        },
+                    )
+                    .await
+                    .flatten()
+            }
+            Err(why) => Err(why.into()),
+        };
+        match res {
+            Ok((msg, turn)) => {
+                let _ = command
+                    .message(&ctx.http, msg)
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(|_| "successfully finished kick command.");
+                if let Some(turn) = turn {
+                    announce_turn(&ctx.http, command.channel_id, turn).await;
                 }
             }
+            Err(why) => {
+                let _ = command
+                    .message(&ctx.http, format!("{why}"))
+                    .await
+                    .with_context(|| anyhow!("ERROR: fail to interaction"))
+                    .logging_with(move |_| format!("{why}"));
+            }
+        }
+    })
+}
+
+fn reveal(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: reveal");
+        if !check_moderator(&ctx.http, &command).await {
+            return;
+        }
+        let msg = GAMES
+            .command(
+                command.channel_id,
+                settings_of(command.guild_id).language,
+                |quiz| {
+                    let answer = quiz.get_answer_regex();
+                    format!(
+                        "The answer is `{}` (size {}).",
+                        answer.simplify(),
+                        answer.size()
+                    )
+                },
+            )
+            .await
+            .unwrap_or_else(|why| format!("{why}"));
+        let _ = command
+            .ephemeral(&ctx.http, msg)
+            .await
+            .with_context(|| anyhow!("ERROR: fail to interaction"))
+            .logging_with(|_| "successfully finished reveal command.");
+    })
+}
+
+fn reset_stats(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: reset-stats");
+        if !check_owner(&ctx.http, &command).await {
+            return;
+        }
+        // the guard must not live across the await
+        let res = FEEDBACK.lock().unwrap().reset();
+        let msg = match &res {
+            Ok(forgotten) => format!("{forgotten} votes are forgotten."),
+            Err(why) => format!("{why}"),
+        };
+        let _ = command
+            .ephemeral(&ctx.http, msg)
+            .await
+            .with_context(|| anyhow!("ERROR: fail to interaction"))
+            .logging_with(|_| "successfully finished reset-stats command.");
+        let _ = res
+            .with_context(|| anyhow!("ERROR: fail to reset the feedback"))
+            .logging_with(|forgotten| format!("forgot {forgotten} votes."));
+    })
+}
+
+fn help(
+    ctx: serenity::client::Context,
+    command: ApplicationCommandInteraction,
+    _: Dictionary,
+) -> HandlerFuture {
+    Box::pin(async move {
+        tracing::debug!("cmd: help");
+        let _ = command
+            .embed(&ctx.http, commands::help(&REGISTRY))
+            .await
+            .with_context(|| anyhow!("ERROR: fail to interaction"))
+            .logging_with(|_| "successfully finished help command.");
+    })
+}
+
+async fn suggest(http: &Http, interaction: &AutocompleteInteraction, suggestions: Vec<Suggestion>) {
+    let _ = interaction
+        .create_autocomplete_response(http, |response| {
+            for suggestion in &suggestions {
+                response.add_string_choice(&suggestion.label, &suggestion.value);
+            }
+            response
+        })
+        .await
+        .with_context(|| anyhow!("ERROR: fail to autocomplete"))
+        .logging_with(|_| "successfully finished autocomplete.");
+}
+
+fn suggest_queries(
+    ctx: serenity::client::Context,
+    interaction: AutocompleteInteraction,
+    input: String,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let user = interaction.user.id.into();
+        let suggestions = GAMES
+            .command(
+                interaction.channel_id,
+                settings_of(interaction.guild_id).language,
+                move |quiz| commands::query_suggestions(quiz, user, &input),
+            )
+            .await
+            .unwrap_or_default();
+        suggest(&ctx.http, &interaction, suggestions).await;
+    })
+}
+
+fn suggest_guesses(
+    ctx: serenity::client::Context,
+    interaction: AutocompleteInteraction,
+    input: String,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let suggestions = GAMES
+            .command(
+                interaction.channel_id,
+                settings_of(interaction.guild_id).language,
+                move |quiz| commands::guess_suggestions(quiz, &input),
+            )
+            .await
+            .unwrap_or_default();
+        suggest(&ctx.http, &interaction, suggestions).await;
+    })
+}
+
+/// Handler for the BOT
+#[derive(Debug)]
+struct Handler;
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: serenity::client::Context, _ready: Ready) {
+        HEALTH.set_ready();
+        let _ = REGISTRY.register(&ctx.http).await.logging_with(|commands| {
+            format!("successfully registered {} commands.", commands.len())
+        });
+        tracing::info!("successfully connected!!");
+        let commands = ApplicationCommand::get_global_application_commands(&ctx.http).await;
+        if let Ok(commands) = &commands {
+            let _ = REGISTRY
+                .cleanup(&ctx.http, commands)
+                .await
+                .logging_with(|_| "successfully cleaned up stale commands.");
+        }
+        tracing::debug!("I now have the following global slash commands: {commands:#?}");
+    }
+
+    async fn resume(&self, _: serenity::client::Context, _: ResumedEvent) {
+        HEALTH.set_connected(true);
+    }
+
+    async fn shard_stage_update(&self, _: serenity::client::Context, event: ShardStageUpdateEvent) {
+        tracing::info!("shard {}: {} -> {}", event.shard_id.0, event.old, event.new);
+        HEALTH.set_connected(event.new == ConnectionStage::Connected);
+    }
+
+    async fn interaction_create(&self, ctx: serenity::client::Context, interaction: Interaction) {
+        if let Some(command) = interaction.clone().application_command() {
+            let _ = REGISTRY
+                .dispatch(ctx, command)
+                .map(|spec| spec.name)
+                .logging_with(|name| format!("dispatched {name} command."));
+        } else if let Some(autocomplete) = interaction.clone().autocomplete() {
+            let _ = REGISTRY
+                .autocomplete(ctx, autocomplete)
+                .map(|option| option.name)
+                .logging_with(|name| format!("dispatched autocomplete of {name}."));
         } else if let Some(component) = interaction.clone().message_component() {
-            let data = component.data.parse().unwrap();
-            match data {
-                CustomId::Feedback { label, regex } => {
-                    println!("{regex} => {label}");
-                    let _ = component
-                        .message(&ctx.http, "ありがとうございました")
-                        .await
-                        .with_context(|| anyhow!("ERROR: fail to interaction"))
-                        .logging_with(|_| "successfully finished feedback.")
-                        .await;
+            let span = tracing::info_span!(
+                "component",
+                guild = ?component.guild_id.map(|id| id.0),
+                channel = component.channel_id.0,
+                user = component.user.id.0,
+            );
+            handle_component(ctx, component).instrument(span).await;
+        }
+    }
+}
+
+/// Handles the buttons and the select menus.
+async fn handle_component(ctx: serenity::client::Context, component: MessageComponentInteraction) {
+    let data = component.data.parse();
+    match data {
+        CustomId::Feedback { vote, game } => {
+            let record = match GAMES.record(game).await {
+                Some(record) => record,
+                // older than the records kept
+                None => {
+                    let log = format!("feedback on an unknown game: {game}");
+                    return out_of_date(&ctx.http, &component, log).await;
                 }
+            };
+            tracing::info!(answer = %logging::redact(&record.answer), %vote, "feedback");
+            if config().features.feedback {
+                let feedback = Feedback::new(&record, component.user.id.into(), vote);
+                // the guard must not live across the await
+                let stored = FEEDBACK.lock().unwrap().record(feedback);
+                let _ = stored
+                    .with_context(|| anyhow!("ERROR: fail to store feedback"))
+                    .logging_with(|_| "successfully stored feedback.");
             }
+            let language = settings_of(component.guild_id).language;
+            let _ = component
+                .message(
+                    &ctx.http,
+                    language.pick("ありがとうございました", "Thank you!"),
+                )
+                .await
+                .with_context(|| anyhow!("ERROR: fail to interaction"))
+                .logging_with(|_| "successfully finished feedback.");
+        }
+        CustomId::Legacy { vote, regex } => {
+            tracing::info!(answer = %logging::redact(&regex), %vote, "feedback");
+            let language = settings_of(component.guild_id).language;
+            let _ = component
+                .message(
+                    &ctx.http,
+                    language.pick("ありがとうございました", "Thank you!"),
+                )
+                .await
+                .with_context(|| anyhow!("ERROR: fail to interaction"))
+                .logging_with(|_| "successfully finished feedback.");
+        }
+        CustomId::Unknown(raw) => {
+            out_of_date(&ctx.http, &component, format!("unknown custom id: {raw}")).await
+        }
+        CustomId::Select {
+            menu: Menu::Start,
+            values,
+        } => {
+            tracing::debug!("menu: start");
+            let _ = component
+                .defer(&ctx.http)
+                .await
+                .with_context(|| anyhow!("ERROR: fail to defer"))
+                .logging_with(|_| "successfully deferred start menu.");
+            let settings = settings_of(component.guild_id);
+            let choice = values
+                .first()
+                .ok_or_else(|| anyhow!("nothing is selected"))
+                .and_then(|value| commands::parse_start_choice(value))
+                .and_then(|(difficulty, mode)| {
+                    Ok((difficulty, settings.rules(Some(mode), false, false)?))
+                });
+            let res = match choice {
+                Ok((difficulty, rules)) => {
+                    let channel = if settings.thread {
+                        let message = component.get_interaction_response(&ctx.http).await;
+                        open_thread(&ctx.http, component.channel_id, message).await
+                    } else {
+                        component.channel_id
+                    };
+                    start_game(&ctx.http, channel, &settings, difficulty, rules).await
+                }
+                Err(why) => Err(why),
+            };
+            let _ = component
+                .edit(&ctx.http, res.unwrap_or_else(|why| why.as_embed()).into())
+                .await
+                .with_context(|| anyhow!("ERROR: fail to interaction"))
+                .logging_with(|_| "successfully finished start menu.");
         }
     }
 }
 
+/// Tells the user that the component no longer works.
+async fn out_of_date(http: &Http, component: &MessageComponentInteraction, log: String) {
+    let language = settings_of(component.guild_id).language;
+    let _ = component
+        .ephemeral(
+            http,
+            language.pick(
+                "このボタンは古くなっています",
+                "This button is out of date.",
+            ),
+        )
+        .await
+        .with_context(|| anyhow!("ERROR: fail to interaction"))
+        .logging_with(move |_| log);
+}
+
 pub async fn build_bot_client(
     token: impl AsRef<str>,
     application_id: u64,
@@ -519,46 +1583,53 @@ pub async fn build_bot_client(
         .with_context(|| anyhow!("ERROR: failed to build client"))
 }
 
-/// Sender/Receiver
-pub static CENTRAL: Lazy<Tsx<Msg>> = Lazy::new(|| {
-    let (sender, receiver) = channel(8);
-    Tsx {
-        sender: Arc::new(sender),
-        receiver: Arc::new(Mutex::new(receiver)),
-    }
-});
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Configure the client with your Discord bot token in the environment.
-    let token = std::env::var("REGEX_SOUP_TOKEN").expect("`REGEX_SOUP_TOKEN` is not found");
+    // `regexsoup serve [ADDR]` serves the HTTP API instead of the BOT
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some("serve") = args.first().map(String::as_str) {
+        let addr = args
+            .get(1)
+            .map_or("127.0.0.1:8080", String::as_str)
+            .parse()
+            .with_context(|| anyhow!("invalid address: {:?}", args.get(1)))?;
+        let config = Config::load()?;
+        let _guard = logging::init(&config.logging)?;
+        return regexsoup::serve::serve(addr, config).await;
+    }
 
+    let config = CONFIG.get_or_try_init(Config::load)?;
+    // flushes the log files when dropped
+    let _guard = logging::init(&config.logging)?;
     // The Application Id is usually the Bot User Id.
-    let application_id = std::env::var("REGEX_SOUP_ID")
-        .expect("`REGEX_SOUP_ID` is not found")
-        .parse::<u64>()
-        .unwrap();
+    let (token, application_id) = config.credentials()?;
 
-    // spawn bot client
+    if config.status.enabled {
+        let address = config.status.address;
+        tokio::spawn(async move {
+            let _ = health::serve(address, health::router(|| GAMES.len()))
+                .await
+                .with_context(|| anyhow!("ERROR: fail to serve the status"))
+                .logging();
+        });
+    }
+
+    let mut client = build_bot_client(token, application_id).await?;
+    let http = Arc::clone(&client.cache_and_http.http);
+    tokio::spawn(sweep(Arc::clone(&http)));
+    let shard_manager = Arc::clone(&client.shard_manager);
     tokio::spawn(async move {
-        let mut client = build_bot_client(token, application_id)
-            .await
-            .expect("client");
-        if let Err(why) = client.start().await {
-            println!("{why:#?}");
-        }
+        health::terminated().await;
+        tracing::info!("shutting down");
+        HEALTH.set_shutting_down();
+        shut_down(&http).await;
+        // `client.start` returns once all the shards are closed
+        shard_manager.lock().await.shutdown_all().await;
     });
-
-    // lock receiver
-    if let Ok(ref mut guardian) = CENTRAL.receiver().try_lock() {
-        let rx = &mut *guardian;
-        // streaming
-        while let Some(msg) = rx.recv().await {
-            match msg {
-                Msg::Ok(log) => println!("{log}"),
-                Msg::Err(why) => println!("{why:#?}"),
-            }
-        }
-    }
+    client
+        .start()
+        .await
+        .with_context(|| anyhow!("ERROR: the client has stopped"))?;
+    tracing::info!("successfully shut down.");
     Ok(())
 }
@@ -0,0 +1,217 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Metrics of the games in the text format of Prometheus, served at `/metrics`
+//!
+//! They tell whether the difficulty settings are sane,
+//! e.g. the solve rate is `regexsoup_games_finished_total{solved="true"}` over all finished games.
+
+use crate::engine::{GameRecord, Quiz};
+use anyhow::Context;
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder, TEXT_FORMAT,
+};
+use std::time::Duration;
+
+/// Metrics of the process, shared by the BOT and the HTTP API
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    games_started: IntCounterVec,
+    games_finished: IntCounterVec,
+    generation_attempts: Histogram,
+    generation_seconds: Histogram,
+    generation_timeouts: IntCounterVec,
+    queries_per_game: Histogram,
+    guesses_per_game: Histogram,
+    active_sessions: IntGauge,
+    sessions_reaped: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let counter = |name, help, labels: &[&str]| {
+            IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric")
+        };
+        let histogram = |name, help, buckets| {
+            Histogram::with_opts(HistogramOpts::new(name, help).buckets(buckets))
+                .expect("valid metric")
+        };
+        let metrics = Metrics {
+            registry: Registry::new(),
+            games_started: counter(
+                "regexsoup_games_started_total",
+                "Games started",
+                &["size", "mode"],
+            ),
+            games_finished: counter(
+                "regexsoup_games_finished_total",
+                "Games finished, whether someone has got AC",
+                &["size", "solved"],
+            ),
+            generation_attempts: histogram(
+                "regexsoup_generation_attempts",
+                "Candidates generated per accepted problem",
+                exponential_buckets(1.0, 2.0, 12).expect("valid buckets"),
+            ),
+            generation_seconds: histogram(
+                "regexsoup_generation_seconds",
+                "Time to generate a problem",
+                exponential_buckets(0.01, 2.0, 12).expect("valid buckets"),
+            ),
+            generation_timeouts: counter(
+                "regexsoup_generation_timeouts_total",
+                "Generations given up for taking too long",
+                &["size"],
+            ),
+            queries_per_game: histogram(
+                "regexsoup_queries_per_game",
+                "Distinct queries in a finished game",
+                vec![0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0],
+            ),
+            guesses_per_game: histogram(
+                "regexsoup_guesses_per_game",
+                "Valid guesses in a finished game",
+                vec![0.0, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 50.0],
+            ),
+            active_sessions: IntGauge::new("regexsoup_active_sessions", "Games running now")
+                .expect("valid metric"),
+            sessions_reaped: IntCounter::new(
+                "regexsoup_sessions_reaped_total",
+                "Games closed for being idle",
+            )
+            .expect("valid metric"),
+        };
+        let registry = &metrics.registry;
+        for collector in vec![
+            Box::new(metrics.games_started.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(metrics.games_finished.clone()),
+            Box::new(metrics.generation_attempts.clone()),
+            Box::new(metrics.generation_seconds.clone()),
+            Box::new(metrics.generation_timeouts.clone()),
+            Box::new(metrics.queries_per_game.clone()),
+            Box::new(metrics.guesses_per_game.clone()),
+            Box::new(metrics.active_sessions.clone()),
+            Box::new(metrics.sessions_reaped.clone()),
+        ] {
+            registry.register(collector).expect("unique metric");
+        }
+        metrics
+    }
+
+    /// Records a game generated in `elapsed`, which is about to start.
+    pub fn generated(&self, quiz: &Quiz, elapsed: Duration) {
+        let size = quiz.record().size.to_string();
+        let mode = quiz.rules().mode.to_string();
+        self.games_started.with_label_values(&[&size, &mode]).inc();
+        self.generation_attempts
+            .observe(quiz.generation_attempts() as f64);
+        self.generation_seconds.observe(elapsed.as_secs_f64());
+    }
+
+    pub fn generation_timed_out(&self, size: u8) {
+        self.generation_timeouts
+            .with_label_values(&[&size.to_string()])
+            .inc();
+    }
+
+    pub fn finished(&self, record: &GameRecord) {
+        self.games_finished
+            .with_label_values(&[&record.size.to_string(), &record.solved.to_string()])
+            .inc();
+        self.queries_per_game.observe(record.queries as f64);
+        self.guesses_per_game.observe(record.guesses as f64);
+    }
+
+    pub fn reaped(&self, sessions: usize) {
+        self.sessions_reaped.inc_by(sessions as u64);
+    }
+
+    /// All the metrics in the text format, with the number of the games running now.
+    pub fn render(&self, active_sessions: usize) -> anyhow::Result<String> {
+        self.active_sessions.set(active_sessions as i64);
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .context("fail to encode the metrics")?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+impl Metrics {
+    /// Response of `/metrics`
+    pub fn respond(&self, active_sessions: usize) -> Response {
+        match self.render(active_sessions) {
+            Ok(text) => ([(header::CONTENT_TYPE, TEXT_FORMAT)], text).into_response(),
+            Err(why) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{why:#}")).into_response(),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+    use crate::{
+        engine::{Quiz, Rules},
+        regex::{GenerationParams, Neutral},
+    };
+    use std::{convert::TryInto, time::Duration};
+
+    #[test]
+    fn metrics_are_rendered() {
+        let metrics = Metrics::new();
+        let quiz = Quiz::generate(
+            2u8.try_into().unwrap(),
+            Rules::default(),
+            &GenerationParams::default(),
+            &Neutral,
+        );
+        assert!(quiz.generation_attempts() >= 1);
+        metrics.generated(&quiz, Duration::from_millis(30));
+        metrics.finished(&quiz.record());
+        metrics.generation_timed_out(10);
+        metrics.reaped(2);
+
+        let text = metrics.render(3).unwrap();
+        for line in vec![
+            r#"regexsoup_games_started_total{mode="normal",size="2"} 1"#,
+            r#"regexsoup_games_finished_total{size="2",solved="false"} 1"#,
+            r#"regexsoup_generation_timeouts_total{size="10"} 1"#,
+            "regexsoup_generation_attempts_count 1",
+            "regexsoup_queries_per_game_sum 0",
+            "regexsoup_active_sessions 3",
+            "regexsoup_sessions_reaped_total 2",
+        ] {
+            assert!(text.lines().any(|rendered| rendered == line), "{}", line);
+        }
+    }
+}
@@ -17,10 +17,7 @@
  *
  */
 
-use crate::concepts::SameAs;
-use serenity::model::{interactions::application_command, user::User};
-
-type OptionValue = serenity::model::interactions::application_command::ApplicationCommandInteractionDataOptionValue;
+use serenity::model::interactions::application_command;
 
 #[derive(Debug, Clone)]
 pub enum SlashCommand {
@@ -40,57 +37,3 @@ pub enum Notification {
     SlashCommand(SlashCommand),
     Component(Component),
 }
-
-pub trait To<Target> {
-    fn to<T>(&self) -> anyhow::Result<Target>
-    where
-        T: SameAs<Target>;
-}
-
-impl To<String> for Notification {
-    fn to<T>(&self) -> anyhow::Result<String>
-    where
-        T: SameAs<String>,
-    {
-        if let Notification::SlashCommand(SlashCommand::Option(boxed)) = self {
-            if let OptionValue::String(value) = &**boxed {
-                return Ok(value.clone());
-            }
-        }
-        Err(anyhow::anyhow!(
-            "cannot convert self to String: {:?}",
-            &self
-        ))
-    }
-}
-
-impl To<i64> for Notification {
-    fn to<T>(&self) -> anyhow::Result<i64>
-    where
-        T: SameAs<i64>,
-    {
-        if let Notification::SlashCommand(SlashCommand::Option(boxed)) = self {
-            if let OptionValue::Integer(value) = &**boxed {
-                return Ok(*value);
-            }
-        }
-        Err(anyhow::anyhow!(
-            "cannot convert self to String: {:?}",
-            &self
-        ))
-    }
-}
-
-impl To<User> for Notification {
-    fn to<T>(&self) -> anyhow::Result<User>
-    where
-        T: SameAs<User>,
-    {
-        if let Notification::SlashCommand(SlashCommand::Option(boxed)) = self {
-            if let OptionValue::User(user, ..) = &**boxed {
-                return Ok(user.clone());
-            }
-        }
-        Err(anyhow::anyhow!("cannot convert self to User {:?}", &self))
-    }
-}
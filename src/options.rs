@@ -0,0 +1,355 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Typed options of slash commands
+//!
+//! A struct of options is declared with [command_options], and parsed from the [Dictionary].
+//!
+//! ```ignore
+//! command_options! {
+//!     pub struct StartOptions {
+//!         #[default = NonZeroU8::new(3).unwrap()]
+//!         size: NonZeroU8,
+//!         mode: Option<GameMode>,
+//!     }
+//! }
+//! let options = StartOptions::parse(&dictionary)?;
+//! ```
+
+use crate::{
+    engine::GameMode,
+    errors::CommandError,
+    notification::{Notification, SlashCommand},
+    regex::ExportFormat,
+    registry::Dictionary,
+    settings::{AllowedModes, Language},
+};
+use serenity::model::{channel::PartialChannel, guild::Role, user::User};
+use std::{convert::TryFrom, num::NonZeroU8};
+
+type OptionValue = serenity::model::interactions::application_command::ApplicationCommandInteractionDataOptionValue;
+
+/// A value of an option
+pub trait FromOption: Sized {
+    /// Shown to the user when the value is invalid
+    const EXPECTED: &'static str;
+
+    fn from_option(value: &OptionValue) -> Option<Self>;
+}
+
+impl FromOption for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromOption for i64 {
+    const EXPECTED: &'static str = "an integer";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromOption for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Number(value) => Some(*value),
+            OptionValue::Integer(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FromOption for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromOption for User {
+    const EXPECTED: &'static str = "a user";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::User(user, ..) => Some(user.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromOption for PartialChannel {
+    const EXPECTED: &'static str = "a channel";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Channel(channel) => Some(channel.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromOption for Role {
+    const EXPECTED: &'static str = "a role";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Role(role) => Some(role.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromOption for NonZeroU8 {
+    const EXPECTED: &'static str = "an integer between 1 and 255";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        i64::from_option(value)
+            .and_then(|value| u8::try_from(value).ok())
+            .and_then(NonZeroU8::new)
+    }
+}
+
+impl FromOption for u64 {
+    const EXPECTED: &'static str = "a non-negative integer";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        i64::from_option(value).and_then(|value| u64::try_from(value).ok())
+    }
+}
+
+impl FromOption for GameMode {
+    const EXPECTED: &'static str = "`normal` or `golf`";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        String::from_option(value).and_then(|mode| mode.parse().ok())
+    }
+}
+
+impl FromOption for ExportFormat {
+    const EXPECTED: &'static str = "`dot` or `mermaid`";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        String::from_option(value).and_then(|format| format.parse().ok())
+    }
+}
+
+impl FromOption for Language {
+    const EXPECTED: &'static str = "`ja` or `en`";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        String::from_option(value).and_then(|language| language.parse().ok())
+    }
+}
+
+impl FromOption for AllowedModes {
+    const EXPECTED: &'static str = "`all`, `normal` or `golf`";
+
+    fn from_option(value: &OptionValue) -> Option<Self> {
+        String::from_option(value).and_then(|modes| modes.parse().ok())
+    }
+}
+
+/// A field of [CommandOptions]; required if `T`, optional if `Option<T>`
+pub trait OptionField: Sized {
+    fn extract(
+        name: &str,
+        value: Option<&Notification>,
+        default: Option<Self>,
+    ) -> Result<Self, CommandError>;
+}
+
+fn value_of<T: FromOption>(
+    name: &str,
+    value: Option<&Notification>,
+) -> Result<Option<T>, CommandError> {
+    match value {
+        None => Ok(None),
+        Some(Notification::SlashCommand(SlashCommand::Option(value))) => T::from_option(value)
+            .map(Some)
+            .ok_or_else(|| CommandError::InvalidOption {
+                name: name.to_string(),
+                expected: T::EXPECTED.to_string(),
+            }),
+        Some(_) => Err(CommandError::InvalidOption {
+            name: name.to_string(),
+            expected: T::EXPECTED.to_string(),
+        }),
+    }
+}
+
+impl<T: FromOption> OptionField for T {
+    fn extract(
+        name: &str,
+        value: Option<&Notification>,
+        default: Option<Self>,
+    ) -> Result<Self, CommandError> {
+        value_of(name, value)?
+            .or(default)
+            .ok_or_else(|| CommandError::MissingOption {
+                name: name.to_string(),
+            })
+    }
+}
+
+impl<T: FromOption> OptionField for Option<T> {
+    fn extract(
+        name: &str,
+        value: Option<&Notification>,
+        default: Option<Self>,
+    ) -> Result<Self, CommandError> {
+        Ok(value_of(name, value)?.or_else(|| default.flatten()))
+    }
+}
+
+/// Looks up the option `name` in `dictionary`.
+pub fn field<T: OptionField>(
+    dictionary: &Dictionary,
+    name: &str,
+    default: Option<T>,
+) -> Result<T, CommandError> {
+    T::extract(name, dictionary.get(name), default)
+}
+
+/// A struct of options, usually declared with [command_options]
+pub trait CommandOptions: Sized {
+    fn parse(dictionary: &Dictionary) -> Result<Self, CommandError>;
+}
+
+/// Declares a struct of options implementing [CommandOptions].
+///
+/// Each field is named after its option.
+/// A field of `Option<T>` is optional, and `#[default = EXPR]` is used when the option is omitted.
+#[macro_export]
+macro_rules! command_options {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[default = $default:expr])?
+                $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(pub $field: $ty,)*
+        }
+
+        impl $crate::options::CommandOptions for $name {
+            fn parse(
+                dictionary: &$crate::registry::Dictionary,
+            ) -> ::std::result::Result<Self, $crate::errors::CommandError> {
+                Ok($name {
+                    $(
+                        $field: $crate::options::field::<$ty>(
+                            dictionary,
+                            stringify!($field),
+                            $crate::command_options!(@default $($default)?),
+                        )?,
+                    )*
+                })
+            }
+        }
+    };
+    (@default) => {
+        None
+    };
+    (@default $default:expr) => {
+        Some($default)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    command_options! {
+        #[derive(Debug, PartialEq)]
+        struct Options {
+            #[default = NonZeroU8::new(3).unwrap()]
+            size: NonZeroU8,
+            mode: Option<GameMode>,
+            input: String,
+        }
+    }
+
+    fn option(value: OptionValue) -> Notification {
+        Notification::SlashCommand(SlashCommand::Option(Box::new(value)))
+    }
+
+    #[test]
+    fn parse_options_with_defaults() {
+        let dictionary = vec![(
+            "input".to_string(),
+            option(OptionValue::String("ab".into())),
+        )]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            Options::parse(&dictionary).unwrap(),
+            Options {
+                size: NonZeroU8::new(3).unwrap(),
+                mode: None,
+                input: "ab".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_options_reports_errors() {
+        let missing = Dictionary::new();
+        assert!(matches!(
+            Options::parse(&missing),
+            Err(CommandError::MissingOption { name }) if name == "input"
+        ));
+
+        let invalid = vec![
+            (
+                "input".to_string(),
+                option(OptionValue::String("ab".into())),
+            ),
+            (
+                "mode".to_string(),
+                option(OptionValue::String("chess".into())),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        assert!(matches!(
+            Options::parse(&invalid),
+            Err(CommandError::InvalidOption { name, .. }) if name == "mode"
+        ));
+    }
+}
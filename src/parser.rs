@@ -18,13 +18,14 @@
  */
 
 use crate::notification::{Notification, SlashCommand};
+pub use crate::response::{CustomId, Menu, Vote};
 
-use serde::{Deserialize, Serialize};
 use serenity::model::interactions::{
     application_command::{
         ApplicationCommandInteractionData, ApplicationCommandInteractionDataOption,
         ApplicationCommandOptionType,
     },
+    autocomplete::AutocompleteInteraction,
     message_component::{ComponentType, MessageComponentInteractionData},
 };
 
@@ -34,7 +35,19 @@ pub trait CommandParser {
     fn parse(&self) -> anyhow::Result<Vec<(String, Notification)>>;
 }
 pub trait ComponentParser {
-    fn parse(&self) -> anyhow::Result<CustomId>;
+    fn parse(&self) -> CustomId;
+}
+pub trait AutocompleteParser {
+    fn parse(&self) -> anyhow::Result<Autocomplete>;
+}
+
+/// The option being typed in an autocomplete interaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Autocomplete {
+    pub command: String,
+    pub option: String,
+    /// incomplete value of the option
+    pub value: String,
 }
 
 /// # Parse an Message Component
@@ -75,6 +88,7 @@ impl CommandParser for ApplicationCommandInteractionData {
                             }
                             Type::String
                             | Type::Integer
+                            | Type::Number
                             | Type::Boolean
                             | Type::User
                             | Type::Channel
@@ -82,7 +96,9 @@ impl CommandParser for ApplicationCommandInteractionData {
                                 ret.push((
                                     option.name.clone(),
                                     Notification::SlashCommand(SlashCommand::Option(Box::new(
-                                        option.resolved.as_ref().unwrap().clone(),
+                                        option.resolved.clone().ok_or_else(|| {
+                                            anyhow::anyhow!("unresolved option: {}", option.name)
+                                        })?,
                                     ))),
                                 ));
                             }
@@ -103,28 +119,50 @@ impl CommandParser for ApplicationCommandInteractionData {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub enum CustomId {
-    Feedback { label: String, regex: String },
-}
-
-impl ToString for CustomId {
-    fn to_string(&self) -> String {
-        serde_json::to_string(&self).expect("valid json")
-    }
-}
-
 /// # Parse an Message Component
 /// Parse an interaction containing messages.
 /// More detail, see [DEVELOPER PORTAL](https://discord.com/developers/docs/interactions/message-components).
 impl ComponentParser for MessageComponentInteractionData {
-    fn parse(&self) -> anyhow::Result<CustomId> {
-        match self.component_type {
+    /// Components not sent by this version are parsed into [CustomId::Unknown].
+    fn parse(&self) -> CustomId {
+        match (self.component_type, CustomId::decode(&self.custom_id)) {
             // [Buttons](https://discord.com/developers/docs/interactions/message-components#buttons)
-            ComponentType::Button => Ok(serde_json::from_str(&self.custom_id)?),
+            (ComponentType::Button, id @ (CustomId::Feedback { .. } | CustomId::Legacy { .. })) => {
+                id
+            }
             // [Select Menus](https://discord.com/developers/docs/interactions/message-components#select-menus)
-            ComponentType::SelectMenu => unimplemented!(),
-            _ => anyhow::bail!("{:?}", &self),
+            (ComponentType::SelectMenu, CustomId::Select { menu, .. }) => CustomId::Select {
+                menu,
+                values: self.values.clone(),
+            },
+            _ => CustomId::Unknown(self.custom_id.clone()),
         }
     }
 }
+
+/// # Parse an Autocomplete
+/// Parse an interaction requesting choices for the focused option.
+/// More detail, see [DEVELOPER PORTAL](https://discord.com/developers/docs/interactions/application-commands#autocomplete).
+impl AutocompleteParser for AutocompleteInteraction {
+    fn parse(&self) -> anyhow::Result<Autocomplete> {
+        fn focused(options: &DataOptions) -> Option<&ApplicationCommandInteractionDataOption> {
+            options
+                .iter()
+                .find(|option| option.focused)
+                .or_else(|| options.iter().find_map(|option| focused(&option.options)))
+        }
+
+        let option = focused(&self.data.options)
+            .ok_or_else(|| anyhow::anyhow!("no focused option: {}", &self.data.name))?;
+        let value = match &option.value {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+        Ok(Autocomplete {
+            command: self.data.name.clone(),
+            option: option.name.clone(),
+            value,
+        })
+    }
+}
@@ -17,8 +17,15 @@
  *
  */
 
+mod automaton;
+mod export;
 mod generate_quiz;
 mod regex_tree;
+mod render;
+mod simplify;
 
+pub use automaton::*;
+pub use export::*;
 pub use generate_quiz::*;
 pub use regex_tree::*;
+pub use render::*;
@@ -0,0 +1,405 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::{Alphabet, RegexAst};
+use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+/// A nondeterministic finite automaton obtained by Thompson's construction.
+///
+/// States are numbered from `0`. A transition labelled with `None` is an ε-transition.
+/// The automaton has exactly one initial state and exactly one accepting state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThompsonNfa {
+    pub initial: usize,
+    pub accepting: usize,
+    /// `transitions[q]` lists the outgoing transitions `(label, target)` of the state `q`
+    pub transitions: Vec<Vec<(Option<Alphabet>, usize)>>,
+}
+
+/// A deterministic finite automaton with a partial transition function.
+///
+/// States are numbered from `0`.
+/// A missing transition denotes a move to the (implicit) dead state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dfa {
+    pub initial: usize,
+    pub accepting: BTreeSet<usize>,
+    /// `transitions[q]` maps a letter to the state reached from the state `q`
+    pub transitions: Vec<BTreeMap<Alphabet, usize>>,
+}
+
+struct ThompsonBuilder {
+    transitions: Vec<Vec<(Option<Alphabet>, usize)>>,
+}
+
+impl ThompsonBuilder {
+    fn state(&mut self) -> usize {
+        self.transitions.push(vec![]);
+        self.transitions.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, label: Option<Alphabet>, to: usize) {
+        self.transitions[from].push((label, to));
+    }
+
+    /// Builds a fragment for `ast` and returns its initial and accepting states.
+    fn fragment(&mut self, ast: &RegexAst) -> (usize, usize) {
+        let initial = self.state();
+        match ast {
+            RegexAst::Epsilon => {
+                let accepting = self.state();
+                self.edge(initial, None, accepting);
+                (initial, accepting)
+            }
+            RegexAst::Literal(a) => {
+                let accepting = self.state();
+                self.edge(initial, Some(*a), accepting);
+                (initial, accepting)
+            }
+            RegexAst::Star(ast) => {
+                let (inner_initial, inner_accepting) = self.fragment(ast);
+                let accepting = self.state();
+                self.edge(initial, None, inner_initial);
+                self.edge(initial, None, accepting);
+                self.edge(inner_accepting, None, inner_initial);
+                self.edge(inner_accepting, None, accepting);
+                (initial, accepting)
+            }
+            RegexAst::Concatenation(asts) => {
+                let accepting = asts.iter().fold(initial, |last, ast| {
+                    let (inner_initial, inner_accepting) = self.fragment(ast);
+                    self.edge(last, None, inner_initial);
+                    inner_accepting
+                });
+                (initial, accepting)
+            }
+            RegexAst::Alternation(asts) => {
+                let fragments = asts.iter().map(|ast| self.fragment(ast)).collect_vec();
+                let accepting = self.state();
+                for (inner_initial, inner_accepting) in fragments {
+                    self.edge(initial, None, inner_initial);
+                    self.edge(inner_accepting, None, accepting);
+                }
+                (initial, accepting)
+            }
+        }
+    }
+}
+
+impl ThompsonNfa {
+    pub fn state_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Letters labelling at least one transition, in ascending order.
+    pub fn alphabets(&self) -> BTreeSet<Alphabet> {
+        self.transitions
+            .iter()
+            .flatten()
+            .filter_map(|(label, _)| *label)
+            .collect()
+    }
+
+    fn epsilon_closure(&self, states: impl IntoIterator<Item = usize>) -> BTreeSet<usize> {
+        let mut closure = BTreeSet::new();
+        let mut stack = states.into_iter().collect_vec();
+
+        while let Some(state) = stack.pop() {
+            if closure.insert(state) {
+                stack.extend(
+                    self.transitions[state]
+                        .iter()
+                        .filter(|(label, _)| label.is_none())
+                        .map(|(_, target)| *target),
+                );
+            }
+        }
+
+        closure
+    }
+
+    /// Converts the automaton into an equivalent [Dfa] by the subset construction.
+    ///
+    /// Only the subsets reachable from the initial state are materialized,
+    /// and the empty subset is left implicit as the dead state.
+    pub fn to_dfa(&self) -> Dfa {
+        let alphabets = self.alphabets();
+        let initial_subset = self.epsilon_closure(vec![self.initial]);
+
+        let mut subsets = vec![initial_subset.clone()];
+        let mut indices = vec![(initial_subset, 0usize)]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let mut transitions = vec![BTreeMap::new()];
+        let mut queue = VecDeque::from(vec![0usize]);
+
+        while let Some(index) = queue.pop_front() {
+            for alphabet in alphabets.iter() {
+                let targets = subsets[index]
+                    .iter()
+                    .flat_map(|state| self.transitions[*state].iter())
+                    .filter(|(label, _)| label.as_ref() == Some(alphabet))
+                    .map(|(_, target)| *target)
+                    .collect_vec();
+
+                if targets.is_empty() {
+                    continue;
+                }
+
+                let subset = self.epsilon_closure(targets);
+                let target = *indices.entry(subset.clone()).or_insert_with(|| {
+                    subsets.push(subset);
+                    transitions.push(BTreeMap::new());
+                    queue.push_back(subsets.len() - 1);
+                    subsets.len() - 1
+                });
+                transitions[index].insert(*alphabet, target);
+            }
+        }
+
+        Dfa {
+            initial: 0,
+            accepting: subsets
+                .iter()
+                .positions(|subset| subset.contains(&self.accepting))
+                .collect(),
+            transitions,
+        }
+    }
+}
+
+impl Dfa {
+    pub fn state_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Letters labelling at least one transition, in ascending order.
+    pub fn alphabets(&self) -> BTreeSet<Alphabet> {
+        self.transitions
+            .iter()
+            .flat_map(|transition| transition.keys().cloned())
+            .collect()
+    }
+
+    pub fn accepts(&self, input: &[Alphabet]) -> bool {
+        input
+            .iter()
+            .try_fold(self.initial, |state, alphabet| {
+                self.transitions[state].get(alphabet).cloned()
+            })
+            .map_or(false, |state| self.accepting.contains(&state))
+    }
+
+    /// Minimizes the automaton by Moore's partition refinement.
+    ///
+    /// States that cannot reach an accepting state are merged into the implicit dead state,
+    /// and the remaining states are renumbered in breadth-first order from the initial state
+    /// (visiting letters in ascending order).
+    /// Hence two minimal automata over the same letters are equal (in terms of [PartialEq])
+    /// if and only if they accept the same language.
+    pub fn minimize(&self) -> Dfa {
+        let alphabets = self.alphabets().into_iter().collect_vec();
+        let dead = self.state_count();
+        let step = |state: usize, alphabet: &Alphabet| {
+            self.transitions
+                .get(state)
+                .and_then(|transition| transition.get(alphabet).cloned())
+                .unwrap_or(dead)
+        };
+
+        // the dead state is appended so that the transition function becomes total
+        let mut classes = (0..=dead)
+            .map(|state| usize::from(self.accepting.contains(&state)))
+            .collect_vec();
+        let mut class_count = classes.iter().unique().count();
+
+        loop {
+            let signatures = (0..=dead)
+                .map(|state| {
+                    let targets = alphabets
+                        .iter()
+                        .map(|alphabet| classes[step(state, alphabet)])
+                        .collect_vec();
+                    (classes[state], targets)
+                })
+                .collect_vec();
+            let numbering = signatures
+                .iter()
+                .unique()
+                .enumerate()
+                .map(|(class, signature)| (signature, class))
+                .collect::<HashMap<_, _>>();
+            let refined = signatures
+                .iter()
+                .map(|signature| numbering[signature])
+                .collect_vec();
+            let refined_count = numbering.len();
+
+            classes = refined;
+            if refined_count == class_count {
+                break;
+            }
+            class_count = refined_count;
+        }
+
+        let dead_class = classes[dead];
+        let representatives = (0..dead)
+            .map(|state| (classes[state], state))
+            .collect::<HashMap<_, _>>();
+
+        let mut renumbering = HashMap::new();
+        let mut transitions = vec![];
+        let mut accepting = BTreeSet::new();
+        let mut queue = VecDeque::new();
+
+        renumbering.insert(classes[self.initial], 0usize);
+        transitions.push(BTreeMap::new());
+        queue.push_back(classes[self.initial]);
+
+        while let Some(class) = queue.pop_front() {
+            let index = renumbering[&class];
+            if class == dead_class {
+                // the language is empty; only the initial state is left
+                continue;
+            }
+            let representative = representatives[&class];
+            if self.accepting.contains(&representative) {
+                accepting.insert(index);
+            }
+            for alphabet in alphabets.iter() {
+                let target_class = classes[step(representative, alphabet)];
+                if target_class == dead_class {
+                    continue;
+                }
+                let target = *renumbering.entry(target_class).or_insert_with(|| {
+                    transitions.push(BTreeMap::new());
+                    queue.push_back(target_class);
+                    transitions.len() - 1
+                });
+                transitions[index].insert(*alphabet, target);
+            }
+        }
+
+        Dfa {
+            initial: 0,
+            accepting,
+            transitions,
+        }
+    }
+}
+
+impl RegexAst {
+    /// Compiles the AST to a [ThompsonNfa].
+    pub fn to_thompson_nfa(&self) -> ThompsonNfa {
+        let mut builder = ThompsonBuilder {
+            transitions: vec![],
+        };
+        let (initial, accepting) = builder.fragment(self);
+
+        ThompsonNfa {
+            initial,
+            accepting,
+            transitions: builder.transitions,
+        }
+    }
+
+    /// Compiles the AST to the minimal [Dfa] over its [used_alphabets](RegexAst::used_alphabets).
+    pub fn to_minimal_dfa(&self) -> Dfa {
+        self.to_thompson_nfa().to_dfa().minimize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::regex::{Alphabet, RegexAst};
+    use itertools::Itertools;
+
+    #[test]
+    fn automata_accept_the_same_language() {
+        let regexes = vec!["ab|c", "ε|a", "a*bεcc*", "ε*", "(a|b)*a", "(ab|b)*(c|ε)"];
+        let inputs = vec![
+            "", "a", "b", "ab", "ba", "bc", "aab", "abbc", "aabccc", "bbabc",
+        ];
+
+        for regex_str in regexes {
+            let ast = RegexAst::parse_str(regex_str).unwrap();
+            let dfa = ast.to_thompson_nfa().to_dfa();
+            let minimal = dfa.minimize();
+
+            for input_str in inputs.iter() {
+                let input = Alphabet::vec_from_str(input_str).unwrap();
+                assert_eq!(
+                    ast.matches(&input),
+                    dfa.accepts(&input),
+                    "The DFA of \"{}\" disagrees on \"{}\"",
+                    regex_str,
+                    input_str
+                );
+                assert_eq!(
+                    ast.matches(&input),
+                    minimal.accepts(&input),
+                    "The minimal DFA of \"{}\" disagrees on \"{}\"",
+                    regex_str,
+                    input_str
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn minimal_dfa_is_minimal_and_canonical() {
+        let state_counts = vec![
+            ("ε", 1),
+            ("a", 2),
+            ("(a|b)*", 1),
+            ("(a|b)*a", 2),
+            ("a*b*", 2),
+        ];
+
+        for (regex_str, state_count) in state_counts {
+            let dfa = RegexAst::parse_str(regex_str).unwrap().to_minimal_dfa();
+            assert_eq!(
+                dfa.state_count(),
+                state_count,
+                "The minimal DFA of \"{}\" should have {} states",
+                regex_str,
+                state_count
+            );
+        }
+
+        let equivalents = vec![("(a|b)*", "a*(ba*)*"), ("(a|b)*a", "(a|b)*baa*|aa*")];
+        for (regex_str_1, regex_str_2) in equivalents {
+            let [dfa_1, dfa_2] = [regex_str_1, regex_str_2]
+                .map(|regex_str| RegexAst::parse_str(regex_str).unwrap().to_minimal_dfa());
+            assert_eq!(dfa_1, dfa_2);
+        }
+
+        assert_eq!(
+            RegexAst::parse_str("ab")
+                .unwrap()
+                .to_minimal_dfa()
+                .transitions
+                .iter()
+                .map(|transition| transition.len())
+                .collect_vec(),
+            vec![1, 1, 0]
+        );
+    }
+}
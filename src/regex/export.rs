@@ -0,0 +1,367 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::{Dfa, RegexAst, ThompsonNfa};
+use anyhow::anyhow;
+use itertools::Itertools;
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Text formats of graphs
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// [Graphviz DOT](https://graphviz.org/doc/info/lang.html)
+    Dot,
+    /// [Mermaid](https://mermaid-js.github.io/mermaid/)
+    Mermaid,
+}
+
+impl ExportFormat {
+    /// File extension of a document in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Dot => "dot",
+            ExportFormat::Mermaid => "md",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(ExportFormat::Dot),
+            "mermaid" => Ok(ExportFormat::Mermaid),
+            _ => Err(anyhow!("unknown export format: {s}")),
+        }
+    }
+}
+
+/// Graphs that can be exported as texts
+pub trait Export {
+    /// Exports the graph as a DOT `digraph` named `name`.
+    fn to_dot(&self, name: &str) -> String;
+
+    /// Exports the graph as a Mermaid diagram.
+    fn to_mermaid(&self) -> String;
+
+    fn export(&self, name: &str, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Dot => self.to_dot(name),
+            ExportFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+}
+
+/// Nodes of the syntax tree in pre-order, paired with the index of their parents.
+fn syntax_tree_nodes(ast: &RegexAst) -> Vec<(&'static str, String, Option<usize>)> {
+    fn visit(
+        ast: &RegexAst,
+        parent: Option<usize>,
+        nodes: &mut Vec<(&'static str, String, Option<usize>)>,
+    ) {
+        let index = nodes.len();
+        let (kind, label, children): (_, _, &[RegexAst]) = match ast {
+            RegexAst::Epsilon => ("epsilon", "ε".to_string(), &[]),
+            RegexAst::Literal(a) => ("literal", a.to_string(), &[]),
+            RegexAst::Star(ast) => ("star", "*".to_string(), std::slice::from_ref(&**ast)),
+            RegexAst::Concatenation(asts) => ("concatenation", "·".to_string(), asts),
+            RegexAst::Alternation(asts) => ("alternation", "|".to_string(), asts),
+        };
+        nodes.push((kind, label, parent));
+        for child in children {
+            visit(child, Some(index), nodes);
+        }
+    }
+
+    let mut nodes = vec![];
+    visit(ast, None, &mut nodes);
+    nodes
+}
+
+/// Exports the syntax tree.
+impl Export for RegexAst {
+    fn to_dot(&self, name: &str) -> String {
+        let nodes = syntax_tree_nodes(self);
+        let mut lines = vec![
+            format!("digraph {name} {{"),
+            "    node [shape=box];".to_string(),
+        ];
+        for (index, (kind, label, _)) in nodes.iter().enumerate() {
+            let shape = if *kind == "literal" || *kind == "epsilon" {
+                "ellipse"
+            } else {
+                "box"
+            };
+            lines.push(format!(r#"    n{index} [label="{label}", shape={shape}];"#));
+        }
+        for (index, (_, _, parent)) in nodes.iter().enumerate() {
+            if let Some(parent) = parent {
+                lines.push(format!("    n{parent} -> n{index};"));
+            }
+        }
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+
+    fn to_mermaid(&self) -> String {
+        let nodes = syntax_tree_nodes(self);
+        let mut lines = vec!["graph TD".to_string()];
+        for (index, (_, label, _)) in nodes.iter().enumerate() {
+            // `|` has to be escaped as an entity code in Mermaid labels
+            let label = label.replace('|', "#124;");
+            lines.push(format!(r#"    n{index}["{label}"]"#));
+        }
+        for (index, (_, _, parent)) in nodes.iter().enumerate() {
+            if let Some(parent) = parent {
+                lines.push(format!("    n{parent} --> n{index}"));
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Common representation of automata for exporting:
+/// the initial state, accepting states and labelled edges.
+struct Diagram {
+    initial: usize,
+    accepting: Vec<usize>,
+    edges: BTreeMap<(usize, usize), Vec<String>>,
+}
+
+impl Diagram {
+    fn to_dot(&self, name: &str) -> String {
+        let mut lines = vec![
+            format!("digraph {name} {{"),
+            "    rankdir=LR;".to_string(),
+            "    node [shape=circle];".to_string(),
+            "    start [shape=point];".to_string(),
+        ];
+        for state in self.accepting.iter() {
+            lines.push(format!("    {state} [shape=doublecircle];"));
+        }
+        lines.push(format!("    start -> {};", self.initial));
+        for ((from, to), labels) in self.edges.iter() {
+            let label = labels.iter().join(",");
+            lines.push(format!(r#"    {from} -> {to} [label="{label}"];"#));
+        }
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut lines = vec![
+            "stateDiagram-v2".to_string(),
+            "    direction LR".to_string(),
+            format!("    [*] --> s{}", self.initial),
+        ];
+        for ((from, to), labels) in self.edges.iter() {
+            let label = labels.iter().join(",");
+            lines.push(format!("    s{from} --> s{to} : {label}"));
+        }
+        for state in self.accepting.iter() {
+            lines.push(format!("    s{state} --> [*]"));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+impl From<&ThompsonNfa> for Diagram {
+    fn from(nfa: &ThompsonNfa) -> Self {
+        Diagram {
+            initial: nfa.initial,
+            accepting: vec![nfa.accepting],
+            edges: nfa
+                .transitions
+                .iter()
+                .enumerate()
+                .flat_map(|(from, transition)| {
+                    transition.iter().map(move |(label, to)| {
+                        let label = label.map_or_else(|| "ε".to_string(), |a| a.to_string());
+                        ((from, *to), label)
+                    })
+                })
+                .into_group_map()
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl From<&Dfa> for Diagram {
+    fn from(dfa: &Dfa) -> Self {
+        Diagram {
+            initial: dfa.initial,
+            accepting: dfa.accepting.iter().cloned().collect(),
+            edges: dfa
+                .transitions
+                .iter()
+                .enumerate()
+                .flat_map(|(from, transition)| {
+                    transition
+                        .iter()
+                        .map(move |(alphabet, to)| ((from, *to), alphabet.to_string()))
+                })
+                .into_group_map()
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl Export for ThompsonNfa {
+    fn to_dot(&self, name: &str) -> String {
+        Diagram::from(self).to_dot(name)
+    }
+
+    fn to_mermaid(&self) -> String {
+        Diagram::from(self).to_mermaid()
+    }
+}
+
+impl Export for Dfa {
+    fn to_dot(&self, name: &str) -> String {
+        Diagram::from(self).to_dot(name)
+    }
+
+    fn to_mermaid(&self) -> String {
+        Diagram::from(self).to_mermaid()
+    }
+}
+
+impl RegexAst {
+    /// Exports the syntax tree, the Thompson NFA and the minimal DFA of the AST
+    /// into a single document.
+    ///
+    /// A DOT document contains three `digraph`s,
+    /// and a Mermaid document is a Markdown with three `mermaid` code blocks.
+    pub fn export_document(&self, format: ExportFormat) -> String {
+        let graphs = [
+            ("Syntax tree", self.export("syntax_tree", format)),
+            (
+                "Thompson NFA",
+                self.to_thompson_nfa().export("thompson_nfa", format),
+            ),
+            (
+                "Minimal DFA",
+                self.to_minimal_dfa().export("minimal_dfa", format),
+            ),
+        ];
+
+        match format {
+            ExportFormat::Dot => graphs
+                .iter()
+                .map(|(title, graph)| format!("// {title} of `{self}`\n{graph}"))
+                .join("\n"),
+            ExportFormat::Mermaid => graphs
+                .iter()
+                .map(|(title, graph)| format!("## {title} of `{self}`\n\n```mermaid\n{graph}```\n"))
+                .join("\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::regex::{Export, ExportFormat, RegexAst};
+    use indoc::indoc;
+
+    #[test]
+    fn export_syntax_tree() {
+        let ast = RegexAst::parse_str("a*|b").unwrap();
+
+        assert_eq!(
+            ast.to_dot("tree"),
+            indoc! {r#"
+                digraph tree {
+                    node [shape=box];
+                    n0 [label="|", shape=box];
+                    n1 [label="*", shape=box];
+                    n2 [label="a", shape=ellipse];
+                    n3 [label="b", shape=ellipse];
+                    n0 -> n1;
+                    n1 -> n2;
+                    n0 -> n3;
+                }
+            "#}
+        );
+
+        assert_eq!(
+            ast.to_mermaid(),
+            indoc! {r##"
+                graph TD
+                    n0["#124;"]
+                    n1["*"]
+                    n2["a"]
+                    n3["b"]
+                    n0 --> n1
+                    n1 --> n2
+                    n0 --> n3
+            "##}
+        );
+    }
+
+    #[test]
+    fn export_minimal_dfa() {
+        let dfa = RegexAst::parse_str("(a|b)*a").unwrap().to_minimal_dfa();
+
+        assert_eq!(
+            dfa.to_dot("dfa"),
+            indoc! {r#"
+                digraph dfa {
+                    rankdir=LR;
+                    node [shape=circle];
+                    start [shape=point];
+                    1 [shape=doublecircle];
+                    start -> 0;
+                    0 -> 0 [label="b"];
+                    0 -> 1 [label="a"];
+                    1 -> 0 [label="b"];
+                    1 -> 1 [label="a"];
+                }
+            "#}
+        );
+
+        assert_eq!(
+            dfa.to_mermaid(),
+            indoc! {r#"
+                stateDiagram-v2
+                    direction LR
+                    [*] --> s0
+                    s0 --> s0 : b
+                    s0 --> s1 : a
+                    s1 --> s0 : b
+                    s1 --> s1 : a
+                    s1 --> [*]
+            "#}
+        );
+    }
+
+    #[test]
+    fn export_document() {
+        let ast = RegexAst::parse_str("ab*").unwrap();
+
+        let dot = ast.export_document(ExportFormat::Dot);
+        assert_eq!(dot.matches("digraph").count(), 3);
+
+        let mermaid = ast.export_document(ExportFormat::Mermaid);
+        assert_eq!(mermaid.matches("```mermaid").count(), 3);
+        assert!(mermaid.contains("stateDiagram-v2"));
+    }
+}
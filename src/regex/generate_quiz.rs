@@ -20,7 +20,7 @@
 use super::RegexAst;
 use crate::regex::Alphabet;
 use itertools::Itertools;
-use rand::{distributions::Slice, Rng};
+use rand::{distributions::Slice, rngs::StdRng, Rng, SeedableRng};
 use rand_distr::{Binomial, Distribution, Uniform, WeightedIndex};
 use std::num::NonZeroU8;
 use strum::IntoEnumIterator;
@@ -35,6 +35,29 @@ pub struct AlphabetSet(pub Vec<Alphabet>);
 const MAX_QUIZ_TREE_SIZE: u8 = 12;
 const MINIMUM_ALLOWED_ACCEPTANCE_RATE: f64 = 0.25;
 const MAXIMUM_ALLOWED_ACCEPTANCE_RATE: f64 = 0.8;
+const ACCEPTANCE_SAMPLE_SIZE: usize = 1000;
+
+/// Tunable parameters of the generation, defaulting to the constants above
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationParams {
+    pub max_tree_size: u8,
+    /// acceptance rates of problems are kept within `(min_acceptance_rate, max_acceptance_rate)`
+    pub min_acceptance_rate: f64,
+    pub max_acceptance_rate: f64,
+    /// number of random words to estimate acceptance rates
+    pub sample_size: usize,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        GenerationParams {
+            max_tree_size: MAX_QUIZ_TREE_SIZE,
+            min_acceptance_rate: MINIMUM_ALLOWED_ACCEPTANCE_RATE,
+            max_acceptance_rate: MAXIMUM_ALLOWED_ACCEPTANCE_RATE,
+            sample_size: ACCEPTANCE_SAMPLE_SIZE,
+        }
+    }
+}
 
 struct WordDistribution<L, A>(L, A);
 impl<'a, L: Distribution<usize>, A: Distribution<&'a Alphabet>> Distribution<Vec<Alphabet>>
@@ -59,14 +82,16 @@ fn word_distribution(alphabets: &AlphabetSet) -> impl Distribution<Vec<Alphabet>
     WordDistribution(length_distribution, alphabet_distribution(alphabets))
 }
 
-fn estimate_acceptance_probability(alphabets: &AlphabetSet, regex_ast: &RegexAst) -> f64 {
+fn estimate_acceptance_probability<R: Rng + ?Sized>(
+    alphabets: &AlphabetSet,
+    regex_ast: &RegexAst,
+    sample_size: usize,
+    rng: &mut R,
+) -> f64 {
     let compiled_ast = regex_ast.compile_to_string_regex();
 
-    let thread_rng = rand::thread_rng();
-
-    let sample_size = 1000;
     let matched = word_distribution(alphabets)
-        .sample_iter(thread_rng)
+        .sample_iter(rng)
         .take(sample_size)
         .filter(|w| compiled_ast.is_match(Alphabet::slice_to_plain_string(w).as_str()))
         .count();
@@ -74,11 +99,35 @@ fn estimate_acceptance_probability(alphabets: &AlphabetSet, regex_ast: &RegexAst
     (matched as f64) / (sample_size as f64)
 }
 
-fn good_as_a_quiz_problem(alphabets: &AlphabetSet, ast: &RegexAst) -> bool {
-    let estimated_acceptance = estimate_acceptance_probability(alphabets, ast);
+/// A hook to prefer some problems to others, such as by feedback of players.
+pub trait ProblemScorer {
+    /// Probability in `[0, 1]` to accept `ast`, which has an appropriate acceptance rate.
+    fn score(&self, ast: &RegexAst) -> f64;
+}
+
+/// Accepts every problem with an appropriate acceptance rate.
+pub struct Neutral;
 
-    MINIMUM_ALLOWED_ACCEPTANCE_RATE < estimated_acceptance
-        && estimated_acceptance < MAXIMUM_ALLOWED_ACCEPTANCE_RATE
+impl ProblemScorer for Neutral {
+    fn score(&self, _ast: &RegexAst) -> f64 {
+        1.0
+    }
+}
+
+fn good_as_a_quiz_problem<R: Rng + ?Sized, S: ProblemScorer + ?Sized>(
+    alphabets: &AlphabetSet,
+    ast: &RegexAst,
+    params: &GenerationParams,
+    scorer: &S,
+    rng: &mut R,
+) -> bool {
+    let estimated_acceptance =
+        estimate_acceptance_probability(alphabets, ast, params.sample_size, rng);
+
+    params.min_acceptance_rate < estimated_acceptance
+        && estimated_acceptance < params.max_acceptance_rate
+        // `gen_bool(1.0)` does not consume `rng`, which keeps seeded problems reproducible
+        && rng.gen_bool(scorer.score(ast).clamp(0.0, 1.0))
 }
 
 fn alphabets_used_with(diff: &Difficulty) -> AlphabetSet {
@@ -210,28 +259,97 @@ impl<'a> Distribution<RegexAst> for BoundedRegexAstDistribution<'a> {
     }
 }
 
-fn generate_ast_smaller_than(alphabets: &AlphabetSet, tree_size: u8) -> RegexAst {
-    let mut rng = rand::thread_rng();
-
+fn generate_ast_smaller_than<R: Rng + ?Sized>(
+    alphabets: &AlphabetSet,
+    tree_size: u8,
+    rng: &mut R,
+) -> RegexAst {
     BoundedRegexAstDistribution {
         alphabet_set: alphabets,
         max_tree_size: tree_size,
     }
-    .sample(&mut rng)
+    .sample(rng)
 }
 
 pub fn randomly_generate(diff: &Difficulty) -> RegexAst {
+    randomly_generate_with(diff, &mut rand::thread_rng())
+}
+
+/// Same as [randomly_generate], but the problem is determined by `seed`.
+pub fn randomly_generate_from_seed(diff: &Difficulty, seed: u64) -> RegexAst {
+    randomly_generate_with(diff, &mut StdRng::seed_from_u64(seed))
+}
+
+pub fn randomly_generate_with<R: Rng + ?Sized>(diff: &Difficulty, rng: &mut R) -> RegexAst {
+    randomly_generate_scored(diff, &GenerationParams::default(), &Neutral, rng)
+}
+
+/// Same as [randomly_generate_with], but generated with `params`
+/// and problems are accepted according to `scorer`.
+pub fn randomly_generate_scored<R: Rng + ?Sized, S: ProblemScorer + ?Sized>(
+    diff: &Difficulty,
+    params: &GenerationParams,
+    scorer: &S,
+    rng: &mut R,
+) -> RegexAst {
+    randomly_generate_counted(diff, params, scorer, rng).0
+}
+
+/// Same as [randomly_generate_scored], but also returns the number of the generated candidates.
+pub fn randomly_generate_counted<R: Rng + ?Sized, S: ProblemScorer + ?Sized>(
+    diff: &Difficulty,
+    params: &GenerationParams,
+    scorer: &S,
+    rng: &mut R,
+) -> (RegexAst, usize) {
+    randomly_generate_until(diff, params, scorer, rng, |_| false).expect("never given up")
+}
+
+/// Same as [randomly_generate_counted], but gives up with `None`
+/// once `give_up` holds for the number of the candidates generated so far.
+pub fn randomly_generate_until<R, S, G>(
+    diff: &Difficulty,
+    params: &GenerationParams,
+    scorer: &S,
+    rng: &mut R,
+    mut give_up: G,
+) -> Option<(RegexAst, usize)>
+where
+    R: Rng + ?Sized,
+    S: ProblemScorer + ?Sized,
+    G: FnMut(usize) -> bool,
+{
     let alphabets = alphabets_used_with(diff);
 
+    let mut attempts = 0;
     loop {
-        let ast = generate_ast_smaller_than(&alphabets, MAX_QUIZ_TREE_SIZE);
+        if give_up(attempts) {
+            return None;
+        }
+        attempts += 1;
+        let ast = generate_ast_smaller_than(&alphabets, params.max_tree_size, rng);
 
-        if good_as_a_quiz_problem(&alphabets, &ast) {
-            return ast.flatten();
+        if good_as_a_quiz_problem(&alphabets, &ast, params, scorer, rng) {
+            return Some((ast.simplify(), attempts));
         }
     }
 }
 
+/// Number of random words to estimate acceptance rates in [is_reachable]
+const REACHABILITY_SAMPLE_SIZE: usize = 200;
+
+/// Whether a problem of `diff` is found with `params` within `attempts` candidates.
+/// The candidates are seeded, so that the result is reproducible,
+/// and their acceptance rates are estimated roughly, so that it is quick.
+pub fn is_reachable(diff: &Difficulty, params: &GenerationParams, attempts: usize) -> bool {
+    let params = GenerationParams {
+        sample_size: params.sample_size.min(REACHABILITY_SAMPLE_SIZE),
+        ..*params
+    };
+    let rng = &mut StdRng::seed_from_u64(0);
+    randomly_generate_until(diff, &params, &Neutral, rng, |tried| tried >= attempts).is_some()
+}
+
 #[test]
 fn difficulty_affects_alphabet_set() {
     assert_eq!(
@@ -256,6 +374,32 @@ fn difficulty_affects_alphabet_set() {
     );
 }
 
+#[test]
+fn unreachable_problems_are_given_up() {
+    use std::convert::TryInto;
+
+    let diff = Difficulty(3u8.try_into().unwrap());
+    // no acceptance rate is in the empty range
+    let impossible = GenerationParams {
+        min_acceptance_rate: 0.5,
+        max_acceptance_rate: 0.5,
+        ..GenerationParams::default()
+    };
+    let rng = &mut rand::thread_rng();
+    assert!(
+        randomly_generate_until(&diff, &impossible, &Neutral, rng, |tried| tried >= 50).is_none()
+    );
+    assert!(!is_reachable(&diff, &impossible, 50));
+
+    // a literal or an epsilon accepts too few words
+    let tiny = GenerationParams {
+        max_tree_size: 1,
+        ..GenerationParams::default()
+    };
+    assert!(!is_reachable(&diff, &tiny, 200));
+    assert!(is_reachable(&diff, &GenerationParams::default(), 1000));
+}
+
 #[test]
 fn randomly_generate_returns() {
     use std::convert::TryInto;
@@ -269,7 +413,81 @@ fn randomly_generate_returns() {
         println!(
             "Generated AST\n\t{:?}\nwith estimated acceptance rate of {}",
             ast,
-            estimate_acceptance_probability(&alphabets, &ast)
+            estimate_acceptance_probability(
+                &alphabets,
+                &ast,
+                ACCEPTANCE_SAMPLE_SIZE,
+                &mut rand::thread_rng()
+            )
+        );
+    }
+}
+
+#[test]
+fn randomly_generate_from_seed_is_reproducible() {
+    use std::convert::TryInto;
+
+    let diff = Difficulty(3u8.try_into().unwrap());
+
+    for seed in 0..3 {
+        assert_eq!(
+            randomly_generate_from_seed(&diff, seed),
+            randomly_generate_from_seed(&diff, seed)
+        );
+    }
+}
+
+#[test]
+fn scorer_rejects_problems() {
+    use std::convert::TryInto;
+
+    /// Rejects a specific problem
+    struct Reject(RegexAst);
+    impl ProblemScorer for Reject {
+        fn score(&self, ast: &RegexAst) -> f64 {
+            if ast.simplify() == self.0 {
+                0.0
+            } else {
+                1.0
+            }
+        }
+    }
+
+    let diff = Difficulty(3u8.try_into().unwrap());
+    let neutral = randomly_generate_from_seed(&diff, 0);
+    let scored = randomly_generate_scored(
+        &diff,
+        &GenerationParams::default(),
+        &Reject(neutral.clone()),
+        &mut StdRng::seed_from_u64(0),
+    );
+    assert_ne!(neutral, scored);
+}
+
+#[test]
+fn simplification_preserves_equivalence() {
+    use std::convert::TryInto;
+
+    let alphabets = alphabets_used_with(&Difficulty(3u8.try_into().unwrap()));
+    let mut rng = rand::thread_rng();
+    let asts = std::iter::repeat_with(|| {
+        generate_ast_smaller_than(&alphabets, MAX_QUIZ_TREE_SIZE, &mut rng)
+    })
+    .take(200);
+
+    for ast in asts {
+        let simplified = ast.simplify();
+        assert!(
+            ast.equivalent_to(&simplified),
+            "The expression \"{}\" should be equivalent to the simplified \"{}\"",
+            ast,
+            simplified
+        );
+        assert_eq!(
+            simplified.simplify(),
+            simplified,
+            "Simplification of \"{}\" should be idempotent",
+            ast
         );
     }
 }
@@ -18,7 +18,9 @@
  */
 
 use anyhow::anyhow;
-use combine::{choice, parser, unexpected_any, value, ParseError, Parser, Stream};
+use combine::{
+    choice, parser, stream::position, unexpected_any, value, EasyParser, ParseError, Parser, Stream,
+};
 use itertools::Itertools;
 use parser::char::{char, letter};
 use rustomaton::{automaton::Buildable, nfa::NFA};
@@ -190,6 +192,15 @@ impl RegexAst {
         }
     }
 
+    /// The column (starting from 1) where `string` fails to be parsed, if any.
+    pub fn syntax_error_at(string: &str) -> Option<usize> {
+        match regex_parser().easy_parse(position::Stream::new(string)) {
+            Ok((_, remaining)) if remaining.input.is_empty() => None,
+            Ok((_, remaining)) => Some(remaining.positioner.column as usize),
+            Err(errors) => Some(errors.position.column as usize),
+        }
+    }
+
     /// Compile the current AST to a regular expression that does not use a ε.
     fn compile_to_epsilonless_regex(&self) -> String {
         fn join_with_separator(sep: &str, asts: &[RegexAst]) -> String {
@@ -257,6 +268,21 @@ impl RegexAst {
         accum
     }
 
+    /// Size of the AST, that is the number of symbols in its string representation
+    /// excluding parentheses.
+    ///
+    /// For example, the size of `(a|bc)*` is 5 (`a`, `|`, `b`, `c` and `*`).
+    pub fn size(&self) -> usize {
+        match self {
+            RegexAst::Epsilon | RegexAst::Literal(_) => 1,
+            RegexAst::Star(ast) => ast.size() + 1,
+            RegexAst::Concatenation(asts) => asts.iter().map(RegexAst::size).sum(),
+            RegexAst::Alternation(asts) => {
+                asts.iter().map(RegexAst::size).sum::<usize>() + asts.len().saturating_sub(1)
+            }
+        }
+    }
+
     pub fn equivalent_to(&self, another: &RegexAst) -> bool {
         let used_alphabets = self.used_alphabets();
         if used_alphabets != another.used_alphabets() {
@@ -345,7 +371,7 @@ impl RegexAst {
             RegexAst::Star(ast) => {
                 let flattened_child = ast.flatten_consecutive_stars();
                 match flattened_child {
-                    RegexAst::Star(grand_child) => *grand_child,
+                    RegexAst::Star(_) => flattened_child,
                     _ => RegexAst::Star(Box::new(flattened_child)),
                 }
             }
@@ -501,6 +527,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn syntax_error_positions() {
+        assert_eq!(RegexAst::syntax_error_at("ab|c"), None);
+        assert_eq!(RegexAst::syntax_error_at("a)b"), Some(2));
+        assert_eq!(RegexAst::syntax_error_at("ab|"), Some(4));
+        assert_eq!(RegexAst::syntax_error_at("(ab"), Some(4));
+    }
+
     #[test]
     fn regex_ast_matches() {
         let positives = vec![
@@ -628,6 +662,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn regex_ast_size() {
+        let pairs = vec![
+            ("ε", 1),
+            ("a", 1),
+            ("a*", 2),
+            ("abc", 3),
+            ("a|b", 3),
+            ("(a|bc)*", 5),
+            ("(a|b)*a", 5),
+            ("((a))", 1),
+        ];
+
+        for (regex_str, size) in pairs {
+            assert_eq!(
+                RegexAst::parse_str(regex_str).unwrap().size(),
+                size,
+                r#"The size of "{}" should be {}"#,
+                regex_str,
+                size
+            )
+        }
+    }
+
     #[test]
     fn regex_ast_equivalence() {
         fn compile_to_regex_ast(regex_str: &str) -> RegexAst {
@@ -680,6 +738,11 @@ mod tests {
             RegexAst::parse_str("a*").unwrap()
         );
 
+        assert_eq!(
+            RegexAst::parse_str("((a)*)*").unwrap().flatten(),
+            RegexAst::parse_str("a*").unwrap()
+        );
+
         assert_eq!(
             RegexAst::Alternation(vec![RegexAst::parse_str("(((a)*)*)*").unwrap()]).flatten(),
             RegexAst::parse_str("a*").unwrap()
@@ -0,0 +1,365 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::{Dfa, RegexAst};
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+use std::collections::{BTreeMap, VecDeque};
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+// constants related to the layout of state diagrams
+const NODE_RADIUS: f32 = 22.0;
+const COLUMN_GAP: f32 = 150.0;
+const ROW_GAP: f32 = 100.0;
+const MARGIN: f32 = 70.0;
+const FONT_SCALE: f32 = 2.0;
+
+/// Glyphs of a 5x7 bitmap font. Each row is stored in the lower 5 bits (MSB is the left-most).
+///
+/// Only the characters appearing in state diagrams are defined;
+/// the others are rendered as `?`.
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'a' => [0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F],
+        'b' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E],
+        'c' => [0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E],
+        'd' => [0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F],
+        'e' => [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E],
+        'f' => [0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08],
+        'g' => [0x00, 0x0F, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'h' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'i' => [0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E],
+        'j' => [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        'ε' => [0x00, 0x00, 0x0F, 0x10, 0x0E, 0x10, 0x0F],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+impl Point {
+    fn new(x: f32, y: f32) -> Self {
+        Point { x, y }
+    }
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+
+    fn scale(self, k: f32) -> Point {
+        Point::new(self.x * k, self.y * k)
+    }
+
+    fn norm(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    fn unit(self) -> Point {
+        self.scale(1.0 / self.norm().max(f32::EPSILON))
+    }
+
+    /// The vector rotated by 90 degrees.
+    fn normal(self) -> Point {
+        Point::new(-self.y, self.x)
+    }
+}
+
+/// Positions of states in a layered layout.
+///
+/// States are placed in columns by their BFS distance from the initial state,
+/// and each column is centered vertically.
+struct Layout {
+    positions: Vec<Point>,
+    depths: Vec<usize>,
+    width: u32,
+    height: u32,
+}
+
+impl Layout {
+    fn of(dfa: &Dfa) -> Layout {
+        let mut depths = vec![usize::MAX; dfa.state_count()];
+        let mut queue = VecDeque::from(vec![dfa.initial]);
+        depths[dfa.initial] = 0;
+
+        while let Some(state) = queue.pop_front() {
+            for target in dfa.transitions[state].values() {
+                if depths[*target] == usize::MAX {
+                    depths[*target] = depths[state] + 1;
+                    queue.push_back(*target);
+                }
+            }
+        }
+
+        // states unreachable from the initial state are put into the last column
+        let last_column = depths.iter().filter(|d| **d != usize::MAX).max().cloned();
+        for depth in depths.iter_mut().filter(|d| **d == usize::MAX) {
+            *depth = last_column.map_or(0, |column| column + 1);
+        }
+
+        let columns = (0..dfa.state_count()).into_group_map_by(|state| depths[*state]);
+        let column_count = columns.keys().max().map_or(1, |max| max + 1);
+        let row_count = columns.values().map(Vec::len).max().unwrap_or(1);
+
+        let mut positions = vec![Point::new(0.0, 0.0); dfa.state_count()];
+        for (depth, states) in columns.iter() {
+            let offset = (row_count - states.len()) as f32 * ROW_GAP / 2.0;
+            for (row, state) in states.iter().sorted().enumerate() {
+                positions[*state] = Point::new(
+                    MARGIN + *depth as f32 * COLUMN_GAP,
+                    MARGIN + offset + row as f32 * ROW_GAP,
+                );
+            }
+        }
+
+        Layout {
+            positions,
+            depths,
+            width: (2.0 * MARGIN + (column_count - 1) as f32 * COLUMN_GAP) as u32,
+            height: (2.0 * MARGIN + (row_count - 1) as f32 * ROW_GAP) as u32,
+        }
+    }
+}
+
+struct Canvas {
+    pixmap: Pixmap,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> anyhow::Result<Canvas> {
+        let mut pixmap = Pixmap::new(width, height)
+            .ok_or_else(|| anyhow!("invalid canvas size: {width}x{height}"))?;
+        pixmap.fill(tiny_skia::Color::WHITE);
+        Ok(Canvas { pixmap })
+    }
+
+    fn paint() -> Paint<'static> {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(32, 32, 32, 255);
+        paint.anti_alias = true;
+        paint
+    }
+
+    fn stroke(&mut self, path: &tiny_skia::Path) {
+        let stroke = Stroke {
+            width: 2.0,
+            ..Stroke::default()
+        };
+        self.pixmap
+            .stroke_path(path, &Self::paint(), &stroke, Transform::identity(), None);
+    }
+
+    fn circle(&mut self, center: Point, radius: f32) {
+        if let Some(path) = PathBuilder::from_circle(center.x, center.y, radius) {
+            self.stroke(&path);
+        }
+    }
+
+    /// Draws a filled arrowhead whose tip is `tip`, pointing to `direction`.
+    fn arrowhead(&mut self, tip: Point, direction: Point) {
+        let back = tip.sub(direction.unit().scale(12.0));
+        let side = direction.unit().normal().scale(5.0);
+
+        let mut builder = PathBuilder::new();
+        builder.move_to(tip.x, tip.y);
+        builder.line_to(back.add(side).x, back.add(side).y);
+        builder.line_to(back.sub(side).x, back.sub(side).y);
+        builder.close();
+
+        if let Some(path) = builder.finish() {
+            self.pixmap.fill_path(
+                &path,
+                &Self::paint(),
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+
+    /// Draws a quadratic curve from `from` to `to` with an arrowhead at `to`.
+    fn arrow(&mut self, from: Point, control: Point, to: Point) {
+        let mut builder = PathBuilder::new();
+        builder.move_to(from.x, from.y);
+        builder.quad_to(control.x, control.y, to.x, to.y);
+        if let Some(path) = builder.finish() {
+            self.stroke(&path);
+        }
+        self.arrowhead(to, to.sub(control));
+    }
+
+    /// Draws `text` with its center at `center`.
+    fn text(&mut self, text: &str, center: Point) {
+        let advance = 6.0 * FONT_SCALE;
+        let width = text.chars().count() as f32 * advance - FONT_SCALE;
+        let left = center.x - width / 2.0;
+        let top = center.y - 3.5 * FONT_SCALE;
+
+        for (index, c) in text.chars().enumerate() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in (0..5).filter(|column| bits & (0x10 >> column) != 0) {
+                    if let Some(rect) = Rect::from_xywh(
+                        left + index as f32 * advance + column as f32 * FONT_SCALE,
+                        top + row as f32 * FONT_SCALE,
+                        FONT_SCALE,
+                        FONT_SCALE,
+                    ) {
+                        self.pixmap
+                            .fill_rect(rect, &Self::paint(), Transform::identity(), None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders `dfa` as a state diagram and encodes it as PNG.
+///
+/// The initial state is marked by an incoming arrow and accepting states by double circles.
+/// Transitions between the same pair of states are merged into a single arrow,
+/// labelled with comma-separated letters.
+pub fn render_dfa_png(dfa: &Dfa) -> anyhow::Result<Vec<u8>> {
+    let layout = Layout::of(dfa);
+    let mut canvas = Canvas::new(layout.width, layout.height)?;
+
+    // initial state
+    let initial = layout.positions[dfa.initial];
+    canvas.arrow(
+        initial.sub(Point::new(2.0 * NODE_RADIUS + 10.0, 0.0)),
+        initial.sub(Point::new(1.5 * NODE_RADIUS, 0.0)),
+        initial.sub(Point::new(NODE_RADIUS, 0.0)),
+    );
+
+    // states
+    for (state, center) in layout.positions.iter().enumerate() {
+        canvas.circle(*center, NODE_RADIUS);
+        if dfa.accepting.contains(&state) {
+            canvas.circle(*center, NODE_RADIUS - 4.0);
+        }
+        canvas.text(&state.to_string(), *center);
+    }
+
+    // transitions
+    let edges = dfa
+        .transitions
+        .iter()
+        .enumerate()
+        .flat_map(|(from, transition)| {
+            transition
+                .iter()
+                .map(move |(alphabet, to)| ((from, *to), alphabet.to_string()))
+        })
+        .into_group_map()
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+
+    for ((from, to), labels) in edges.iter() {
+        let label = labels.iter().join(",");
+        let p0 = layout.positions[*from];
+        let p1 = layout.positions[*to];
+
+        if from == to {
+            let radius = NODE_RADIUS;
+            let start = p0.add(Point::new(-radius / 2.0, -radius * 0.866));
+            let end = p0.add(Point::new(radius / 2.0, -radius * 0.866));
+            let c1 = p0.add(Point::new(-radius, -radius * 2.6));
+            let c2 = p0.add(Point::new(radius, -radius * 2.6));
+
+            let mut builder = PathBuilder::new();
+            builder.move_to(start.x, start.y);
+            builder.cubic_to(c1.x, c1.y, c2.x, c2.y, end.x, end.y);
+            if let Some(path) = builder.finish() {
+                canvas.stroke(&path);
+            }
+            canvas.arrowhead(end, end.sub(c2));
+            canvas.text(&label, p0.add(Point::new(0.0, -radius * 2.6)));
+            continue;
+        }
+
+        let direction = p1.sub(p0);
+        let normal = direction.unit().normal();
+        let adjacent = layout.depths[*to] == layout.depths[*from] + 1;
+        let has_reverse = edges.contains_key(&(*to, *from));
+        // bend arrows that would overlap with the reverse arrow or run across other states
+        let bend = if adjacent && !has_reverse {
+            0.0
+        } else if adjacent {
+            0.15 * direction.norm()
+        } else {
+            0.25 * direction.norm()
+        };
+
+        let control = p0.add(direction.scale(0.5)).add(normal.scale(bend));
+        let start = p0.add(control.sub(p0).unit().scale(NODE_RADIUS));
+        let end = p1.add(control.sub(p1).unit().scale(NODE_RADIUS));
+        canvas.arrow(start, control, end);
+
+        let middle = start
+            .scale(0.25)
+            .add(control.scale(0.5))
+            .add(end.scale(0.25));
+        canvas.text(&label, middle.add(normal.scale(14.0)));
+    }
+
+    canvas
+        .pixmap
+        .encode_png()
+        .with_context(|| anyhow!("failed to encode a state diagram"))
+}
+
+impl RegexAst {
+    /// Renders the [minimal DFA](RegexAst::to_minimal_dfa) of the AST as a PNG image.
+    pub fn render_minimal_dfa_png(&self) -> anyhow::Result<Vec<u8>> {
+        render_dfa_png(&self.to_minimal_dfa())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::regex::RegexAst;
+
+    #[test]
+    fn render_minimal_dfa() {
+        for regex_str in vec!["ε", "a", "(a|b)*a", "(ab|b)*(c|ε)", "a*b*c*"] {
+            let png = RegexAst::parse_str(regex_str)
+                .unwrap()
+                .render_minimal_dfa_png()
+                .unwrap();
+            assert_eq!(&png[1..4], b"PNG");
+        }
+    }
+}
@@ -0,0 +1,239 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::RegexAst;
+use itertools::Itertools;
+
+impl RegexAst {
+    /// Whether the expression matches the empty string.
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            RegexAst::Epsilon | RegexAst::Star(_) => true,
+            RegexAst::Literal(_) => false,
+            RegexAst::Concatenation(asts) => asts.iter().all(RegexAst::is_nullable),
+            RegexAst::Alternation(asts) => asts.iter().any(RegexAst::is_nullable),
+        }
+    }
+
+    /// Whether the language of `self` is a subset of the language of `another`.
+    fn is_subsumed_by(&self, another: &RegexAst) -> bool {
+        RegexAst::Alternation(vec![self.clone(), another.clone()]).equivalent_to(another)
+    }
+
+    fn simplify_star(inner: RegexAst) -> RegexAst {
+        match inner {
+            // ε* → ε
+            RegexAst::Epsilon => RegexAst::Epsilon,
+            // (r*)* → r*
+            RegexAst::Star(_) => inner,
+            // (ε|r|s*)* → (r|s)*
+            RegexAst::Alternation(asts) => {
+                let asts = asts
+                    .into_iter()
+                    .filter(|ast| *ast != RegexAst::Epsilon)
+                    .map(|ast| match ast {
+                        RegexAst::Star(ast) => *ast,
+                        _ => ast,
+                    })
+                    .collect_vec();
+                if asts.is_empty() {
+                    RegexAst::Epsilon
+                } else {
+                    RegexAst::Star(Box::new(Self::simplify_alternation(asts)))
+                }
+            }
+            // (r*s*)* → (r|s)*, since every factor is nullable
+            RegexAst::Concatenation(asts) if asts.iter().all(RegexAst::is_nullable) => {
+                Self::simplify_star(Self::simplify_alternation(asts))
+            }
+            _ => RegexAst::Star(Box::new(inner)),
+        }
+    }
+
+    fn simplify_concatenation(asts: Vec<RegexAst>) -> RegexAst {
+        // rε → r, r(st) → rst
+        let asts = asts
+            .into_iter()
+            .flat_map(|ast| match ast {
+                RegexAst::Epsilon => vec![],
+                RegexAst::Concatenation(asts) => asts,
+                _ => vec![ast],
+            })
+            // r*r* → r*
+            .coalesce(|previous, current| match (&previous, &current) {
+                (RegexAst::Star(_), RegexAst::Star(_)) if previous == current => Ok(previous),
+                _ => Err((previous, current)),
+            })
+            .collect_vec();
+
+        match asts.len() {
+            0 => RegexAst::Epsilon,
+            1 => asts.into_iter().next().unwrap(),
+            _ => RegexAst::Concatenation(asts),
+        }
+    }
+
+    fn simplify_alternation(asts: Vec<RegexAst>) -> RegexAst {
+        // r|(s|t) → r|s|t, r|r → r
+        let mut asts = asts
+            .into_iter()
+            .flat_map(|ast| match ast {
+                RegexAst::Alternation(asts) => asts,
+                _ => vec![ast],
+            })
+            .unique_by(|ast| ast.to_string())
+            .collect_vec();
+
+        if asts.contains(&RegexAst::Epsilon) {
+            // ε|rr* → r*, ε|r*r → r*
+            for ast in asts.iter_mut() {
+                let replacement = match ast {
+                    RegexAst::Concatenation(factors) => match &factors[..] {
+                        [RegexAst::Star(inner), other] | [other, RegexAst::Star(inner)]
+                            if **inner == *other =>
+                        {
+                            Some(RegexAst::Star(inner.clone()))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some(replacement) = replacement {
+                    *ast = replacement;
+                }
+            }
+        }
+
+        // ε|r* → r*
+        if asts
+            .iter()
+            .any(|ast| *ast != RegexAst::Epsilon && ast.is_nullable())
+        {
+            asts.retain(|ast| *ast != RegexAst::Epsilon);
+        }
+
+        // r|s → s if r ⊆ s
+        let mut index = 0;
+        while index < asts.len() {
+            let subsumed = asts
+                .iter()
+                .enumerate()
+                .any(|(another, ast)| another != index && asts[index].is_subsumed_by(ast));
+            if subsumed {
+                asts.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+
+        match asts.len() {
+            1 => asts.into_iter().next().unwrap(),
+            _ => RegexAst::Alternation(asts),
+        }
+    }
+
+    fn simplify_once(&self) -> Self {
+        match self {
+            RegexAst::Epsilon | RegexAst::Literal(_) => self.clone(),
+            RegexAst::Star(ast) => Self::simplify_star(ast.simplify_once()),
+            RegexAst::Concatenation(asts) => {
+                Self::simplify_concatenation(asts.iter().map(RegexAst::simplify_once).collect())
+            }
+            RegexAst::Alternation(asts) => {
+                Self::simplify_alternation(asts.iter().map(RegexAst::simplify_once).collect())
+            }
+        }
+    }
+
+    /// Simplifies the AST by algebraic rewriting.
+    ///
+    /// In addition to [flatten](RegexAst::flatten), this operation repeatedly applies
+    /// the following rules (bottom-up) until none of them changes the AST:
+    ///
+    ///  * ε is removed from a Concatenation. For example, `aεb` will be simplified into `ab`.
+    ///  * Consecutive identical Stars in a Concatenation are merged.
+    ///    For example, `a*a*b` will be simplified into `a*b`.
+    ///  * Duplicated branches of an Alternation are removed.
+    ///    For example, `(a|a)b` will be simplified into `ab`.
+    ///  * ε is removed from an Alternation if another branch matches the empty string.
+    ///    For example, `ε|a*` will be simplified into `a*`.
+    ///  * `ε|rr*` and `ε|r*r` are simplified into `r*`.
+    ///  * A branch of an Alternation is removed if it is subsumed by another branch.
+    ///    For example, `a|ab*` will be simplified into `ab*`.
+    ///  * ε and Stars are removed from an Alternation under a Star.
+    ///    For example, `(ε|a|b*)*` will be simplified into `(a|b)*`.
+    ///  * A Concatenation of nullable expressions under a Star is turned into an Alternation.
+    ///    For example, `(a*b*)*` will be simplified into `(a|b)*`.
+    ///  * `ε*` is simplified into `ε`.
+    ///
+    /// Since [RegexAst] has no syntax sugar for `r+`, `rr*` is left as it is.
+    ///
+    /// This operation preserves the regular expression up to equivalence.
+    /// That is, [equivalent_to](RegexAst::equivalent_to) returns true
+    /// between the original AST and the returned AST.
+    pub fn simplify(&self) -> Self {
+        let mut current = self.flatten();
+        loop {
+            let next = current.simplify_once();
+            if next == current {
+                return current;
+            }
+            current = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::regex::RegexAst;
+
+    #[test]
+    fn regex_ast_simplification() {
+        let pairs = vec![
+            ("aεb", "ab"),
+            ("ε|aa*", "a*"),
+            ("ε|a*a", "a*"),
+            ("(a|a)b", "ab"),
+            ("(ε|a)*", "a*"),
+            ("(ε|a|b*)*", "(a|b)*"),
+            ("(a*b*)*", "(a|b)*"),
+            ("a*a*b", "a*b"),
+            ("ε|a*", "a*"),
+            ("a|ab*", "ab*"),
+            ("(a|b)|(b|a)", "a|b"),
+            ("ε*", "ε"),
+            ("εε", "ε"),
+            ("aa*", "aa*"),
+        ];
+
+        for (regex_str, simplified_str) in pairs {
+            let ast = RegexAst::parse_str(regex_str).unwrap();
+            let simplified = RegexAst::parse_str(simplified_str).unwrap();
+
+            assert_eq!(
+                ast.simplify(),
+                simplified,
+                "The expression \"{}\" should be simplified into \"{}\"",
+                regex_str,
+                simplified_str
+            );
+            assert!(ast.equivalent_to(&simplified));
+        }
+    }
+}
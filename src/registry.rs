@@ -0,0 +1,422 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! The registry of slash commands
+//!
+//! Each command is defined once as a [CommandSpec].
+//! Registration, cleanup of stale commands, help and dispatch are derived from the [Registry].
+
+use crate::{
+    notification::{Notification, SlashCommand},
+    parser::{AutocompleteParser, CommandParser},
+    response::Embed,
+};
+use anyhow::{anyhow, Context};
+use serenity::{
+    client,
+    http::Http,
+    model::interactions::{
+        application_command::{
+            ApplicationCommand, ApplicationCommandInteraction, ApplicationCommandOptionType,
+        },
+        autocomplete::AutocompleteInteraction,
+    },
+};
+use std::{collections::HashMap, future::Future, pin::Pin};
+use tracing::Instrument;
+
+/// Options of an invoked command keyed by their names
+pub type Dictionary = HashMap<String, Notification>;
+
+pub type HandlerFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Async handler of a command
+pub type Handler = fn(client::Context, ApplicationCommandInteraction, Dictionary) -> HandlerFuture;
+
+/// Async handler of an autocomplete, which receives the incomplete value of the option
+pub type AutocompleteHandler =
+    fn(client::Context, AutocompleteInteraction, String) -> HandlerFuture;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    User,
+    Channel,
+    Role,
+}
+
+impl From<OptionKind> for ApplicationCommandOptionType {
+    fn from(kind: OptionKind) -> Self {
+        match kind {
+            OptionKind::String => ApplicationCommandOptionType::String,
+            OptionKind::Integer => ApplicationCommandOptionType::Integer,
+            OptionKind::Number => ApplicationCommandOptionType::Number,
+            OptionKind::Boolean => ApplicationCommandOptionType::Boolean,
+            OptionKind::User => ApplicationCommandOptionType::User,
+            OptionKind::Channel => ApplicationCommandOptionType::Channel,
+            OptionKind::Role => ApplicationCommandOptionType::Role,
+        }
+    }
+}
+
+/// A fixed value of an option, named after itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Choice {
+    Integer(i32),
+    String(&'static str),
+}
+
+impl std::fmt::Display for Choice {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Choice::Integer(value) => write!(f, "{value}"),
+            Choice::String(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: OptionKind,
+    pub required: bool,
+    pub choices: Vec<Choice>,
+    pub autocomplete: Option<AutocompleteHandler>,
+}
+
+impl OptionSpec {
+    pub fn new(name: &'static str, description: &'static str, kind: OptionKind) -> Self {
+        OptionSpec {
+            name,
+            description,
+            kind,
+            required: false,
+            choices: vec![],
+            autocomplete: None,
+        }
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn choices(mut self, choices: impl IntoIterator<Item = Choice>) -> Self {
+        self.choices.extend(choices);
+        self
+    }
+
+    pub fn autocomplete(mut self, handler: AutocompleteHandler) -> Self {
+        self.autocomplete = Some(handler);
+        self
+    }
+}
+
+#[derive(Clone)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Shown in the help in addition to the options
+    pub help: &'static str,
+    pub options: Vec<OptionSpec>,
+    pub handler: Handler,
+}
+
+impl std::fmt::Debug for CommandSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CommandSpec")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("help", &self.help)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl CommandSpec {
+    pub fn new(name: &'static str, description: &'static str, handler: Handler) -> Self {
+        CommandSpec {
+            name,
+            description,
+            help: "",
+            options: vec![],
+            handler,
+        }
+    }
+
+    pub fn help(mut self, help: &'static str) -> Self {
+        self.help = help;
+        self
+    }
+
+    pub fn option(mut self, option: OptionSpec) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    /// `/name [OPTION]...`
+    pub fn usage(&self) -> String {
+        std::iter::once(format!("/{}", self.name))
+            .chain(
+                self.options
+                    .iter()
+                    .map(|option| format!("[{}]", option.name.to_uppercase())),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The help of the command followed by a line per option
+    pub fn help_text(&self) -> String {
+        let help = if self.help.is_empty() {
+            self.description
+        } else {
+            self.help.trim_end()
+        };
+        std::iter::once(help.to_string())
+            .chain(self.options.iter().map(|option| {
+                let choices = if option.choices.is_empty() {
+                    String::new()
+                } else {
+                    let choices = option
+                        .choices
+                        .iter()
+                        .map(|choice| format!("`{choice}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!(" ({choices})")
+                };
+                format!(
+                    "[{}]: {}{choices}",
+                    option.name.to_uppercase(),
+                    option.description
+                )
+            }))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    commands: Vec<CommandSpec>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a command.
+    /// Panics if a command of the same name is already registered.
+    pub fn command(mut self, spec: CommandSpec) -> Self {
+        assert!(
+            self.get(spec.name).is_none(),
+            "duplicated command: {}",
+            spec.name
+        );
+        self.commands.push(spec);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CommandSpec> {
+        self.commands.iter().find(|spec| spec.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CommandSpec> {
+        self.commands.iter()
+    }
+
+    /// Appends a field per command to `embed`.
+    pub fn help(&self, embed: Embed) -> Embed {
+        self.iter().fold(embed, |embed, spec| {
+            embed.field(spec.usage(), spec.help_text(), false)
+        })
+    }
+
+    /// Registers all commands as global slash commands.
+    pub async fn register(
+        &self,
+        http: impl AsRef<Http>,
+    ) -> anyhow::Result<Vec<ApplicationCommand>> {
+        ApplicationCommand::set_global_application_commands(&http, |commands| {
+            for spec in self.iter() {
+                commands.create_application_command(|command| {
+                    command.name(spec.name).description(spec.description);
+                    for option in &spec.options {
+                        command.create_option(|o| {
+                            o.name(option.name)
+                                .description(option.description)
+                                .kind(option.kind.into())
+                                .required(option.required)
+                                .set_autocomplete(option.autocomplete.is_some());
+                            for choice in &option.choices {
+                                match choice {
+                                    Choice::Integer(value) => o.add_int_choice(value, *value),
+                                    Choice::String(value) => o.add_string_choice(value, value),
+                                };
+                            }
+                            o
+                        });
+                    }
+                    command
+                });
+            }
+            commands
+        })
+        .await
+        .with_context(|| anyhow!("serenity error"))
+    }
+
+    /// Deletes the global slash commands which are not in the registry.
+    pub async fn cleanup(
+        &self,
+        http: impl AsRef<Http>,
+        commands: &[ApplicationCommand],
+    ) -> anyhow::Result<()> {
+        for command in commands
+            .iter()
+            .filter(|command| self.get(&command.name).is_none())
+        {
+            ApplicationCommand::delete_global_application_command(&http, command.id)
+                .await
+                .with_context(|| anyhow!("fail to delete `{}`", command.name))?;
+        }
+        Ok(())
+    }
+
+    /// Spawns the handler of the invoked command in a span of the interaction.
+    pub fn dispatch(
+        &self,
+        ctx: client::Context,
+        command: ApplicationCommandInteraction,
+    ) -> anyhow::Result<&CommandSpec> {
+        let flat_data = command.data.parse()?;
+        let (head, tail) = flat_data
+            .split_first()
+            .ok_or_else(|| anyhow!("empty command"))?;
+        let spec = match head {
+            (_, Notification::SlashCommand(SlashCommand::Command(name))) => self
+                .get(name)
+                .ok_or_else(|| anyhow!("unknown command: {name}"))?,
+            (_, unknown) => anyhow::bail!("unknown command: {:?}", unknown),
+        };
+        let dictionary = tail.iter().cloned().collect::<Dictionary>();
+        let span = tracing::info_span!(
+            "command",
+            command = spec.name,
+            guild = ?command.guild_id.map(|id| id.0),
+            channel = command.channel_id.0,
+            user = command.user.id.0,
+        );
+        tokio::task::spawn((spec.handler)(ctx, command, dictionary).instrument(span));
+        Ok(spec)
+    }
+
+    /// Spawns the autocomplete handler of the focused option.
+    pub fn autocomplete(
+        &self,
+        ctx: client::Context,
+        interaction: AutocompleteInteraction,
+    ) -> anyhow::Result<&OptionSpec> {
+        let autocomplete = interaction.parse()?;
+        let option = self
+            .get(&autocomplete.command)
+            .and_then(|spec| {
+                spec.options
+                    .iter()
+                    .find(|option| option.name == autocomplete.option)
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown option: {} {}",
+                    autocomplete.command,
+                    autocomplete.option
+                )
+            })?;
+        let handler = option
+            .autocomplete
+            .ok_or_else(|| anyhow!("no autocomplete: {}", option.name))?;
+        let span = tracing::debug_span!(
+            "autocomplete",
+            command = %autocomplete.command,
+            option = option.name,
+            channel = interaction.channel_id.0,
+            user = interaction.user.id.0,
+        );
+        tokio::task::spawn(handler(ctx, interaction, autocomplete.value).instrument(span));
+        Ok(option)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Colour;
+
+    fn nop(_: client::Context, _: ApplicationCommandInteraction, _: Dictionary) -> HandlerFuture {
+        Box::pin(async {})
+    }
+
+    fn registry() -> Registry {
+        Registry::new()
+            .command(
+                CommandSpec::new("export", "Export the answer.", nop).option(
+                    OptionSpec::new("format", "format of graphs", OptionKind::String)
+                        .required()
+                        .choices([Choice::String("dot"), Choice::String("mermaid")]),
+                ),
+            )
+            .command(CommandSpec::new(
+                "join",
+                "Register your participation.",
+                nop,
+            ))
+    }
+
+    #[test]
+    fn help_is_derived_from_specs() {
+        let embed = registry().help(Embed::new(Colour::DarkGreen, "HELP"));
+        let fields = embed
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                (
+                    "/export [FORMAT]",
+                    "Export the answer.\n[FORMAT]: format of graphs (`dot`, `mermaid`)"
+                ),
+                ("/join", "Register your participation."),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicated command: join")]
+    fn duplicated_commands_are_rejected() {
+        let _ = registry().command(CommandSpec::new("join", "again", nop));
+    }
+}
@@ -18,7 +18,12 @@
  */
 
 mod component;
+mod custom_id;
+mod render;
 mod structure;
+mod text;
 
 pub use component::*;
+pub use custom_id::*;
+pub use render::*;
 pub use structure::*;
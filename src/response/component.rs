@@ -18,38 +18,39 @@
  */
 
 use crate::concepts::{Condition, Satisfied};
+use serde::Serialize;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Component {
-    Buttons {
-        content: String,
-        buttons: Buttons,
-    },
-    SelectMenu {
-        custom_id: String,
-        content: String,
-        placeholder: String,
-        min_value: u64,
-        max_value: u64,
-        options: Vec<SelectMenuOption>,
-    },
+    Buttons { content: String, buttons: Buttons },
+    SelectMenu(SelectMenu),
 }
 
-#[allow(dead_code)]
-#[derive(Debug)]
-pub struct SelectMenuOption {
-    pub description: String,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Success,
+    Danger,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Button {
+    pub style: ButtonStyle,
     pub label: String,
-    pub value: String,
+    pub custom_id: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
 pub struct Buttons {
-    buttons: Vec<serenity::builder::CreateButton>,
+    buttons: Vec<Button>,
 }
 
 impl Buttons {
-    pub fn new<const N: usize>(buttons: &[serenity::builder::CreateButton; N]) -> Buttons
+    pub fn new<const N: usize>(buttons: [Button; N]) -> Buttons
     where
         Condition<{ N <= 5 }>: Satisfied,
     {
@@ -57,13 +58,34 @@ impl Buttons {
             buttons: buttons.to_vec(),
         }
     }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Button> {
+        self.buttons.iter()
+    }
 }
 
 impl IntoIterator for Buttons {
     type IntoIter = std::vec::IntoIter<Self::Item>;
-    type Item = serenity::builder::CreateButton;
+    type Item = Button;
 
     fn into_iter(self) -> Self::IntoIter {
         self.buttons.into_iter()
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelectMenu {
+    pub custom_id: String,
+    pub content: String,
+    pub placeholder: String,
+    pub min_value: u64,
+    pub max_value: u64,
+    pub options: Vec<SelectMenuOption>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelectMenuOption {
+    pub description: String,
+    pub label: String,
+    pub value: String,
+}
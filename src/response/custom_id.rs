@@ -0,0 +1,212 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! `custom_id`s of message components
+//!
+//! A [CustomId] is encoded as a version tag followed by a compact binary payload in base64,
+//! which fits in 100 characters of Discord.
+//! Games are referred by their ids rather than by their answers.
+//! Legacy ids in JSON and unknown ones are decoded into [CustomId::Legacy] and [CustomId::Unknown].
+
+use serde::{Deserialize, Serialize};
+use std::{convert::TryInto, fmt};
+
+/// The current version of the encoding
+const VERSION: u8 = 1;
+
+// tags of the variants
+const FEEDBACK: u8 = 0;
+const SELECT: u8 = 1;
+
+/// Select menus sent by the bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Menu {
+    /// difficulty/mode picker after a bare `/start`
+    Start,
+}
+
+impl Menu {
+    fn to_byte(self) -> u8 {
+        match self {
+            Menu::Start => 0,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Menu::Start),
+            _ => None,
+        }
+    }
+}
+
+/// Feedback on a game as a problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Vote {
+    Good,
+    Bad,
+}
+
+impl fmt::Display for Vote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Vote::Good => write!(f, "good"),
+            Vote::Bad => write!(f, "bad"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomId {
+    /// `game` is the id of a [GameRecord](crate::engine::GameRecord).
+    Feedback { vote: Vote, game: u64 },
+    /// `values` is empty when sent, and is filled with the selected values when parsed.
+    Select { menu: Menu, values: Vec<String> },
+    /// feedback sent in JSON before versioning, which embeds the answer
+    Legacy { vote: Vote, regex: String },
+    /// ids of unknown versions or broken ones
+    Unknown(String),
+}
+
+/// [CustomId] in JSON before versioning
+#[derive(Deserialize)]
+enum LegacyCustomId {
+    Feedback { label: String, regex: String },
+    Select { menu: Menu, values: Vec<String> },
+}
+
+impl CustomId {
+    pub fn encode(&self) -> String {
+        let bytes = match self {
+            CustomId::Feedback { vote, game } => {
+                let vote = match vote {
+                    Vote::Good => 0,
+                    Vote::Bad => 1,
+                };
+                [&[VERSION, FEEDBACK, vote][..], &game.to_be_bytes()].concat()
+            }
+            CustomId::Select { menu, .. } => vec![VERSION, SELECT, menu.to_byte()],
+            CustomId::Legacy { vote, regex } => {
+                return serde_json::json!({
+                    "Feedback": { "label": vote.to_string(), "regex": regex }
+                })
+                .to_string()
+            }
+            CustomId::Unknown(raw) => return raw.clone(),
+        };
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Decodes `raw`, falling back to [CustomId::Unknown].
+    pub fn decode(raw: &str) -> Self {
+        Self::decode_legacy(raw)
+            .or_else(|| Self::decode_versioned(raw))
+            .unwrap_or_else(|| CustomId::Unknown(raw.to_string()))
+    }
+
+    fn decode_versioned(raw: &str) -> Option<Self> {
+        let bytes = base64::decode_config(raw, base64::URL_SAFE_NO_PAD).ok()?;
+        match bytes.as_slice() {
+            [VERSION, FEEDBACK, vote, game @ ..] => {
+                let vote = match vote {
+                    0 => Vote::Good,
+                    1 => Vote::Bad,
+                    _ => return None,
+                };
+                let game = u64::from_be_bytes(game.try_into().ok()?);
+                Some(CustomId::Feedback { vote, game })
+            }
+            [VERSION, SELECT, menu] => Some(CustomId::Select {
+                menu: Menu::from_byte(*menu)?,
+                values: vec![],
+            }),
+            _ => None,
+        }
+    }
+
+    fn decode_legacy(raw: &str) -> Option<Self> {
+        match serde_json::from_str(raw).ok()? {
+            LegacyCustomId::Feedback { label, regex } => {
+                let vote = match label.as_str() {
+                    "good" => Vote::Good,
+                    "bad" => Vote::Bad,
+                    _ => return None,
+                };
+                Some(CustomId::Legacy { vote, regex })
+            }
+            LegacyCustomId::Select { menu, values } => Some(CustomId::Select { menu, values }),
+        }
+    }
+}
+
+impl ToString for CustomId {
+    fn to_string(&self) -> String {
+        self.encode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CustomId, Menu, Vote};
+
+    #[test]
+    fn custom_ids_round_trip_within_the_limit() {
+        let ids = [
+            CustomId::Feedback {
+                vote: Vote::Bad,
+                game: u64::MAX,
+            },
+            CustomId::Select {
+                menu: Menu::Start,
+                values: vec![],
+            },
+        ];
+        for id in ids.iter() {
+            let encoded = id.encode();
+            assert!(encoded.len() <= 100, "{}", encoded);
+            assert_eq!(&CustomId::decode(&encoded), id);
+        }
+    }
+
+    #[test]
+    fn legacy_and_unknown_custom_ids() {
+        assert_eq!(
+            CustomId::decode(r#"{"Feedback":{"label":"good","regex":"a*"}}"#),
+            CustomId::Legacy {
+                vote: Vote::Good,
+                regex: "a*".to_string(),
+            }
+        );
+        assert_eq!(
+            CustomId::decode(r#"{"Select":{"menu":"Start","values":[]}}"#),
+            CustomId::Select {
+                menu: Menu::Start,
+                values: vec![],
+            }
+        );
+        // a future version
+        let future = base64::encode_config([2, 0, 0], base64::URL_SAFE_NO_PAD);
+        assert_eq!(CustomId::decode(&future), CustomId::Unknown(future.clone()));
+        assert_eq!(
+            CustomId::decode("not an id"),
+            CustomId::Unknown("not an id".to_string())
+        );
+    }
+}
@@ -0,0 +1,144 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! The renderer of [Response] into serenity interaction responses, edits and follow-ups
+
+use super::{Button, ButtonStyle, Colour, Component, Embed, Message, Response, SelectMenu};
+use serenity::{
+    builder::{
+        CreateButton, CreateComponents, CreateEmbed, CreateInteractionResponseData,
+        CreateInteractionResponseFollowup, EditInteractionResponse,
+    },
+    model::interactions::message_component,
+    utils,
+};
+
+impl From<Colour> for utils::Colour {
+    fn from(colour: Colour) -> Self {
+        match colour {
+            Colour::BlitzBlue => utils::Colour::BLITZ_BLUE,
+            Colour::DarkBlue => utils::Colour::DARK_BLUE,
+            Colour::DarkGreen => utils::Colour::DARK_GREEN,
+            Colour::Red => utils::Colour::RED,
+        }
+    }
+}
+
+impl From<&Embed> for CreateEmbed {
+    fn from(embed: &Embed) -> Self {
+        let mut create = CreateEmbed::default();
+        create
+            .colour(utils::Colour::from(embed.colour))
+            .title(&embed.title);
+        for field in embed.fields.iter() {
+            create.field(&field.name, &field.value, field.inline);
+        }
+        create
+    }
+}
+
+impl From<ButtonStyle> for message_component::ButtonStyle {
+    fn from(style: ButtonStyle) -> Self {
+        match style {
+            ButtonStyle::Primary => message_component::ButtonStyle::Primary,
+            ButtonStyle::Secondary => message_component::ButtonStyle::Secondary,
+            ButtonStyle::Success => message_component::ButtonStyle::Success,
+            ButtonStyle::Danger => message_component::ButtonStyle::Danger,
+        }
+    }
+}
+
+impl From<&Button> for CreateButton {
+    fn from(button: &Button) -> Self {
+        let mut create = CreateButton::default();
+        create
+            .style(button.style.into())
+            .label(&button.label)
+            .custom_id(&button.custom_id);
+        create
+    }
+}
+
+impl SelectMenu {
+    /// Puts the select menu into an action row.
+    fn create_components<'a>(
+        &self,
+        components: &'a mut CreateComponents,
+    ) -> &'a mut CreateComponents {
+        components.create_action_row(|action_row| {
+            action_row.create_select_menu(|select_menu| {
+                select_menu
+                    .custom_id(&self.custom_id)
+                    .placeholder(&self.placeholder)
+                    .min_values(self.min_value)
+                    .max_values(self.max_value)
+                    .options(|options| {
+                        for option in self.options.iter() {
+                            options.create_option(|o| {
+                                o.label(&option.label)
+                                    .value(&option.value)
+                                    .description(&option.description)
+                            });
+                        }
+                        options
+                    })
+            })
+        })
+    }
+}
+
+/// Rendering into the data of an interaction response, an edit or a follow-up
+pub trait Render<Builder> {
+    fn render<'a>(&self, builder: &'a mut Builder) -> &'a mut Builder;
+}
+
+// The builders have the same methods without a common trait.
+macro_rules! impl_render {
+    ($($builder:ty),*) => {
+        $(
+            impl Render<$builder> for Response {
+                fn render<'a>(&self, data: &'a mut $builder) -> &'a mut $builder {
+                    match self {
+                        Response::Message(Message::String(content)) => data.content(content),
+                        Response::Message(Message::Embed(embed)) => data.add_embed(embed.into()),
+                        Response::Components(Component::Buttons { content, buttons }) => {
+                            data.content(content).components(|components| {
+                                components.create_action_row(|action_row| {
+                                    for button in buttons.iter() {
+                                        action_row.add_button(button.into());
+                                    }
+                                    action_row
+                                })
+                            })
+                        }
+                        Response::Components(Component::SelectMenu(menu)) => data
+                            .content(&menu.content)
+                            .components(|components| menu.create_components(components)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_render!(
+    CreateInteractionResponseData,
+    EditInteractionResponse,
+    CreateInteractionResponseFollowup
+);
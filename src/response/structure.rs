@@ -18,15 +18,80 @@
  */
 
 use super::component::Component;
+use serde::Serialize;
 
-#[derive(Debug)]
+/// Colours of embeds, named after [serenity::utils::Colour]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Colour {
+    BlitzBlue,
+    DarkBlue,
+    DarkGreen,
+    Red,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Embed {
+    pub colour: Colour,
+    pub title: String,
+    pub fields: Vec<Field>,
+}
+
+impl Embed {
+    pub fn new(colour: Colour, title: impl ToString) -> Self {
+        Embed {
+            colour,
+            title: title.to_string(),
+            fields: vec![],
+        }
+    }
+
+    pub fn field(mut self, name: impl ToString, value: impl ToString, inline: bool) -> Self {
+        self.fields.push(Field {
+            name: name.to_string(),
+            value: value.to_string(),
+            inline,
+        });
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Message {
     String(String),
-    Embed(serenity::builder::CreateEmbed),
+    Embed(Embed),
 }
 
-#[derive(Debug)]
+/// Transport-neutral response of a game operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Response {
     Message(Message),
     Components(Component),
 }
+
+impl Response {
+    pub fn message(content: impl ToString) -> Self {
+        Response::Message(Message::String(content.to_string()))
+    }
+}
+
+impl From<Embed> for Response {
+    fn from(embed: Embed) -> Self {
+        Response::Message(Message::Embed(embed))
+    }
+}
+
+impl From<Component> for Response {
+    fn from(component: Component) -> Self {
+        Response::Components(component)
+    }
+}
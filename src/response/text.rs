@@ -0,0 +1,61 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Plain-text rendering of [Response] for terminals
+
+use super::{Component, Embed, Message, Response};
+use itertools::Itertools;
+use std::fmt;
+
+impl fmt::Display for Embed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# {}", self.title)?;
+        for field in self.fields.iter() {
+            writeln!(f, "{}: {}", field.name, field.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Response::Message(Message::String(content)) => writeln!(f, "{}", content.trim_end()),
+            Response::Message(Message::Embed(embed)) => write!(f, "{embed}"),
+            Response::Components(Component::Buttons { content, buttons }) => {
+                writeln!(f, "{}", content.trim_end())?;
+                writeln!(
+                    f,
+                    "{}",
+                    buttons
+                        .iter()
+                        .map(|button| format!("[{}]", button.label))
+                        .join(" ")
+                )
+            }
+            Response::Components(Component::SelectMenu(menu)) => {
+                writeln!(f, "{}", menu.content.trim_end())?;
+                for option in menu.options.iter() {
+                    writeln!(f, "  {}: {}", option.value, option.label)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,794 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! `serve` mode: the game over a local HTTP JSON API
+//!
+//! | method | path                          | body                                      |
+//! |--------|-------------------------------|-------------------------------------------|
+//! | POST   | `/sessions`                   | `{"size"?, "mode"?, "private_queries"?}`  |
+//! | POST   | `/sessions/:session/players`  | `{"name"}`                                |
+//! | POST   | `/sessions/:session/query`    | `{"player", "input"}`                     |
+//! | POST   | `/sessions/:session/guess`    | `{"player", "input"}`                     |
+//! | GET    | `/sessions/:session/summary`  | (`?player=` required in private queries)  |
+//! | POST   | `/sessions/:session/give-up`  | `{"player"}`                              |
+//! | GET    | `/sessions/:session/events`   | (WebSocket)                               |
+//! | GET    | `/metrics`                    | (text format of Prometheus)               |
+//! | GET    | `/healthz`                    |                                           |
+//!
+//! Game operations answer with [Response] in JSON,
+//! and [Event]s of a session are pushed to the WebSocket clients as JSON texts.
+//! The WebSocket streams are closed when the game of the session is over.
+//! Sessions nobody has played for a while are closed with an `expired` event.
+
+use crate::{
+    commands,
+    config::Config,
+    engine::{Actors, Event, GameMode, Player, PlayerId, Quiz, Rules, SessionId},
+    errors::CommandError,
+    health, logging,
+    metrics::METRICS,
+    regex::Neutral,
+    response::Response,
+};
+use anyhow::anyhow;
+use axum::{
+    extract::{
+        ws::{Message, WebSocketUpgrade},
+        Extension, Path, Query,
+    },
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    net::SocketAddr,
+    num::NonZeroU8,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::broadcast;
+
+/// capacity of the event stream of a session
+const EVENT_CAPACITY: usize = 64;
+
+/// An error answered as `{"error": ...}`
+pub struct ApiError {
+    status: StatusCode,
+    error: anyhow::Error,
+}
+
+impl ApiError {
+    fn bad_request(error: anyhow::Error) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            error,
+        }
+    }
+
+    fn not_found(error: anyhow::Error) -> Self {
+        ApiError {
+            status: StatusCode::NOT_FOUND,
+            error,
+        }
+    }
+
+    fn internal(error: anyhow::Error) -> Self {
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error,
+        }
+    }
+
+    /// Failures of the generation are not the fault of the client.
+    fn generation(error: anyhow::Error) -> Self {
+        let status = match error.downcast_ref::<CommandError>() {
+            Some(CommandError::Timeout { .. }) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError { status, error }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        let body = Json(serde_json::json!({ "error": format!("{}", self.error) }));
+        (self.status, body).into_response()
+    }
+}
+
+/// State shared by the handlers
+#[derive(Default)]
+pub struct Server {
+    config: Config,
+    games: Actors<SessionId>,
+    /// players keyed by their ids, with the sessions they have joined
+    players: Mutex<HashMap<PlayerId, (SessionId, Player)>>,
+    events: Mutex<HashMap<SessionId, broadcast::Sender<Event>>>,
+}
+
+impl Server {
+    pub fn new(config: Config) -> Self {
+        Server {
+            config,
+            ..Server::default()
+        }
+    }
+
+    fn player(&self, id: PlayerId) -> Result<Player, ApiError> {
+        self.players
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|(_, player)| player.clone())
+            .ok_or_else(|| ApiError::not_found(anyhow!("unknown player: {id}")))
+    }
+
+    fn subscribe(&self, session: &SessionId) -> Result<broadcast::Receiver<Event>, ApiError> {
+        self.events
+            .lock()
+            .unwrap()
+            .get(session)
+            .map(broadcast::Sender::subscribe)
+            .ok_or_else(|| ApiError::not_found(anyhow!("unknown session: {session}")))
+    }
+
+    /// Pushes `events` to the clients of `session`.
+    fn publish(&self, session: &SessionId, events: Vec<Event>) {
+        for event in &events {
+            tracing::info!(%session, event = ?logging::redact_event(event.clone()), "event");
+        }
+        if let Some(sender) = self.events.lock().unwrap().get(session) {
+            for event in events {
+                // there may be no subscriber
+                let _ = sender.send(event);
+            }
+        }
+    }
+
+    /// Opens a fresh session of `quiz`.
+    async fn open(&self, quiz: Quiz) -> SessionId {
+        let session = SessionId::generate();
+        self.events
+            .lock()
+            .unwrap()
+            .insert(session.clone(), broadcast::channel(EVENT_CAPACITY).0);
+        self.games.start(session.clone(), quiz).await;
+        session
+    }
+
+    /// Runs `cmd` on the game of `session`, and pushes the events occurred meanwhile.
+    /// If `player` is given, the player must be a participant.
+    async fn command<F, R>(
+        &self,
+        session: &SessionId,
+        player: Option<PlayerId>,
+        cmd: F,
+    ) -> Result<R, ApiError>
+    where
+        F: FnOnce(&mut Quiz) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (res, events) = self
+            .games
+            .command(session, move |quiz| {
+                if let Some(player) = player {
+                    if !quiz.is_participant(&player) {
+                        let why = anyhow!("{player} has not joined the game");
+                        return (Err(ApiError::bad_request(why)), vec![]);
+                    }
+                }
+                let res = cmd(quiz).map_err(ApiError::bad_request);
+                (res, quiz.take_events())
+            })
+            .await
+            .ok_or_else(|| ApiError::not_found(anyhow!("no game in the session: {session}")))?;
+        self.publish(session, events);
+        res
+    }
+
+    /// Closes the game of `session`, keeping its answer.
+    async fn finish(&self, session: &SessionId) {
+        self.games.finish(session).await;
+        self.close(session);
+    }
+
+    /// Forgets the players of `session`, and closes its event streams.
+    fn close(&self, session: &SessionId) {
+        // dropping the sender closes the event streams of the session
+        self.events.lock().unwrap().remove(session);
+        self.players
+            .lock()
+            .unwrap()
+            .retain(|_, (joined, _)| joined != session);
+    }
+
+    /// Closes the sessions idle for `idle` or longer, revealing their answers to the clients.
+    /// Returns the number of the closed sessions.
+    pub async fn reap(&self, idle: Duration) -> usize {
+        let reaped = self.games.reap(idle).await;
+        let count = reaped.len();
+        for (session, mut quiz) in reaped {
+            quiz.expire();
+            self.publish(&session, quiz.take_events());
+            self.close(&session);
+        }
+        count
+    }
+
+    /// Number of the sessions closed for being idle so far
+    pub fn reaped(&self) -> usize {
+        self.games.reaped()
+    }
+}
+
+/// Closes idle sessions periodically.
+async fn sweep(server: Arc<Server>) {
+    let timeouts = server.config.timeouts.clone();
+    let mut interval = tokio::time::interval(timeouts.sweep());
+    loop {
+        interval.tick().await;
+        let reaped = server.reap(timeouts.idle()).await;
+        if reaped > 0 {
+            tracing::info!(reaped, total = server.reaped(), "reaped idle sessions");
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateSession {
+    size: Option<u8>,
+    mode: Option<String>,
+    private_queries: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct SessionCreated {
+    session: SessionId,
+    response: Response,
+}
+
+#[derive(Deserialize)]
+struct Join {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Joined {
+    player: PlayerId,
+}
+
+#[derive(Deserialize)]
+struct Input {
+    player: PlayerId,
+    input: String,
+}
+
+#[derive(Deserialize)]
+struct Summary {
+    player: Option<PlayerId>,
+}
+
+#[derive(Deserialize)]
+struct GiveUp {
+    player: PlayerId,
+}
+
+async fn create_session(
+    Extension(server): Extension<Arc<Server>>,
+    Json(body): Json<CreateSession>,
+) -> Result<Json<SessionCreated>, ApiError> {
+    let config = &server.config;
+    let size: NonZeroU8 = match body.size {
+        Some(size) => size
+            .try_into()
+            .map_err(|_| ApiError::bad_request(anyhow!("size must be positive")))?,
+        None => config.generation.default_size(),
+    };
+    let mode = match body.mode {
+        Some(mode) => mode
+            .parse()
+            .map_err(|why| ApiError::bad_request(anyhow!("{why}")))?,
+        None => GameMode::Normal,
+    };
+    // turns are not supported since nobody watches their time limits
+    let rules = Rules {
+        mode,
+        turn_based: false,
+        private_queries: body.private_queries.unwrap_or(false),
+        ..Rules::default()
+    };
+    let quiz = commands::generate_regex(
+        size,
+        rules,
+        config.generation.params(),
+        Arc::new(Neutral),
+        config.timeouts.generation(),
+    )
+    .await
+    .map_err(ApiError::generation)?;
+
+    let response = quiz.introduction().into();
+    let session = server.open(quiz).await;
+    Ok(Json(SessionCreated { session, response }))
+}
+
+async fn join(
+    Extension(server): Extension<Arc<Server>>,
+    Path(session): Path<SessionId>,
+    Json(body): Json<Join>,
+) -> Result<Json<Joined>, ApiError> {
+    let player = Player {
+        id: PlayerId(rand::random()),
+        name: body.name,
+    };
+    let id = player.id;
+    server
+        .command(&session, None, move |quiz| quiz.register(id))
+        .await?;
+    server
+        .players
+        .lock()
+        .unwrap()
+        .insert(player.id, (session, player.clone()));
+    Ok(Json(Joined { player: player.id }))
+}
+
+async fn query(
+    Extension(server): Extension<Arc<Server>>,
+    Path(session): Path<SessionId>,
+    Json(body): Json<Input>,
+) -> Result<Json<Response>, ApiError> {
+    server
+        .command(&session, Some(body.player), move |quiz| {
+            quiz.check_turn(&body.player)?;
+            let response = quiz.query(body.player, &body.input)?;
+            quiz.end_turn();
+            Ok(response)
+        })
+        .await
+        .map(Json)
+}
+
+async fn guess(
+    Extension(server): Extension<Arc<Server>>,
+    Path(session): Path<SessionId>,
+    Json(body): Json<Input>,
+) -> Result<Json<Response>, ApiError> {
+    let player = server.player(body.player)?;
+    let id = player.id;
+    let guess = server
+        .command(&session, Some(id), move |quiz| {
+            quiz.check_turn(&id)?;
+            quiz.guess(&body.input)
+        })
+        .await?;
+    // the equivalence check is CPU-bound, so it runs out of the game
+    let verdict = guess.judge_blocking().await.map_err(ApiError::internal)?;
+    let (response, solved) = server
+        .command(&session, Some(player.id), move |quiz| {
+            quiz.check_turn(&player.id)?;
+            let response = quiz.settle(&player, verdict)?;
+            quiz.end_turn();
+            Ok((response, quiz.is_solved()))
+        })
+        .await?;
+    if solved {
+        server.finish(&session).await;
+    }
+    Ok(Json(response))
+}
+
+async fn summary(
+    Extension(server): Extension<Arc<Server>>,
+    Path(session): Path<SessionId>,
+    Query(params): Query<Summary>,
+) -> Result<Json<Response>, ApiError> {
+    server
+        .command(&session, params.player, move |quiz| {
+            match (params.player, quiz.rules().private_queries) {
+                (Some(player), _) => Ok(quiz.get_query_history(player)),
+                (None, true) => Err(anyhow!("`player` is required in private queries")),
+                // the shared history is visible to anyone
+                (None, false) => Ok(quiz.get_query_history(PlayerId(0))),
+            }
+        })
+        .await
+        .map(Json)
+}
+
+async fn give_up(
+    Extension(server): Extension<Arc<Server>>,
+    Path(session): Path<SessionId>,
+    Json(body): Json<GiveUp>,
+) -> Result<Json<Response>, ApiError> {
+    let player = server.player(body.player)?;
+    let (response, revealed) = server
+        .command(&session, Some(player.id), move |quiz| {
+            let response = quiz.accepts_give_up(&player)?;
+            Ok((response, quiz.is_empty()))
+        })
+        .await?;
+    if revealed {
+        server.finish(&session).await;
+    }
+    Ok(Json(response))
+}
+
+async fn events(
+    ws: WebSocketUpgrade,
+    Extension(server): Extension<Arc<Server>>,
+    Path(session): Path<SessionId>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut receiver = server.subscribe(&session)?;
+    Ok(ws.on_upgrade(|mut socket| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let text = serde_json::to_string(&event).expect("valid json");
+            if socket.send(Message::Text(text)).await.is_err() {
+                // the client has gone
+                break;
+            }
+        }
+    }))
+}
+
+async fn metrics(Extension(server): Extension<Arc<Server>>) -> impl IntoResponse {
+    METRICS.respond(server.games.len())
+}
+
+pub fn router(server: Arc<Server>) -> Router {
+    Router::new()
+        .route("/sessions", post(create_session))
+        .route("/sessions/:session/players", post(join))
+        .route("/sessions/:session/query", post(query))
+        .route("/sessions/:session/guess", post(guess))
+        .route("/sessions/:session/summary", get(summary))
+        .route("/sessions/:session/give-up", post(give_up))
+        .route("/sessions/:session/events", get(events))
+        .route("/metrics", get(metrics))
+        .route("/healthz", get(health::healthz))
+        .layer(Extension(server))
+}
+
+/// Serves the API on `addr` until SIGTERM or Ctrl-C, finishing the requests in flight.
+pub async fn serve(addr: SocketAddr, config: Config) -> anyhow::Result<()> {
+    let state = Arc::new(Server::new(config));
+    tokio::spawn(sweep(Arc::clone(&state)));
+    let server = axum::Server::try_bind(&addr)?.serve(router(state).into_make_service());
+    tracing::info!("serving the API on http://{}", server.local_addr());
+    server.with_graceful_shutdown(health::terminated()).await?;
+    tracing::info!("shut down the API");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{router, ApiError, Server};
+    use crate::{
+        engine::{Quiz, Rules},
+        errors::CommandError,
+        regex::RegexAst,
+    };
+    use axum::http::StatusCode;
+    use futures_util::StreamExt;
+    use serde_json::{json, Value};
+    use std::{convert::TryInto, net::SocketAddr, sync::Arc, time::Duration};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// Sends a request over a plain TCP connection, and returns the status and the JSON body.
+    async fn request(addr: SocketAddr, method: &str, path: &str, body: Value) -> (u16, Value) {
+        let body = body.to_string();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: \
+             application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn serve_a_session_on_localhost() {
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(router(Arc::new(Server::default())).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let (status, created) = request(addr, "POST", "/sessions", json!({ "size": 2 })).await;
+        assert_eq!(status, 200);
+        let session = created["session"].as_str().unwrap().to_string();
+
+        let (status, joined) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/players"),
+            json!({ "name": "alice" }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        let player = joined["player"].clone();
+
+        let (status, queried) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/query"),
+            json!({ "player": player, "input": "ab" }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert!(queried["message"]["string"]
+            .as_str()
+            .unwrap()
+            .starts_with("ab => "));
+
+        let (status, error) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/query"),
+            json!({ "player": player, "input": "xyz" }),
+        )
+        .await;
+        assert_eq!(status, 400);
+        assert!(error["error"].is_string());
+
+        let (status, summary) = request(
+            addr,
+            "GET",
+            &format!("/sessions/{session}/summary"),
+            json!({}),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(summary["message"]["embed"]["fields"][0]["name"], "ab");
+
+        let (status, revealed) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/give-up"),
+            json!({ "player": player }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert!(revealed["components"]["buttons"]["content"].is_string());
+
+        let (status, _) = request(
+            addr,
+            "GET",
+            &format!("/sessions/{session}/summary"),
+            json!({}),
+        )
+        .await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn private_queries_need_a_player() {
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(router(Arc::new(Server::default())).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let (_, created) = request(
+            addr,
+            "POST",
+            "/sessions",
+            json!({ "size": 2, "private_queries": true }),
+        )
+        .await;
+        let session = created["session"].as_str().unwrap().to_string();
+        let (_, joined) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/players"),
+            json!({ "name": "alice" }),
+        )
+        .await;
+        let player = joined["player"].as_u64().unwrap();
+        request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/query"),
+            json!({ "player": player, "input": "ab" }),
+        )
+        .await;
+
+        let summary = format!("/sessions/{session}/summary");
+        let (status, _) = request(addr, "GET", &summary, json!({})).await;
+        assert_eq!(status, 400);
+        let (status, history) = request(
+            addr,
+            "GET",
+            &format!("{summary}?player={player}"),
+            json!({}),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(history["message"]["embed"]["fields"][0]["name"], "ab");
+    }
+
+    #[tokio::test]
+    async fn guesses_are_judged() {
+        let state = Arc::new(Server::default());
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(router(Arc::clone(&state)).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let answer = RegexAst::parse_str("(a|b)*").unwrap();
+        let quiz = Quiz::with_answer(answer, 2u8.try_into().unwrap(), Rules::default());
+        let session = state.open(quiz).await;
+        let (_, joined) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/players"),
+            json!({ "name": "alice" }),
+        )
+        .await;
+        let player = joined["player"].clone();
+
+        let guess = format!("/sessions/{session}/guess");
+        for (input, expected) in [("a*", "a* => WA"), ("(a*b*)*", "(a*b*)* => AC")].iter() {
+            let (status, judged) = request(
+                addr,
+                "POST",
+                &guess,
+                json!({ "player": player, "input": input }),
+            )
+            .await;
+            assert_eq!(status, 200);
+            assert_eq!(judged["message"]["string"], *expected);
+        }
+        // the solved game is over
+        let (status, _) = request(
+            addr,
+            "POST",
+            &guess,
+            json!({ "player": player, "input": "a" }),
+        )
+        .await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn idle_sessions_are_reaped() {
+        let state = Arc::new(Server::default());
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(router(Arc::clone(&state)).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let (_, created) = request(addr, "POST", "/sessions", json!({ "size": 2 })).await;
+        let session = created["session"].as_str().unwrap().to_string();
+        assert_eq!(state.reap(Duration::from_secs(60)).await, 0);
+        assert_eq!(state.reap(Duration::ZERO).await, 1);
+        assert_eq!(state.reaped(), 1);
+
+        let (status, _) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/players"),
+            json!({ "name": "alice" }),
+        )
+        .await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn events_are_streamed_until_the_game_ends() {
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(router(Arc::new(Server::default())).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let (_, created) = request(addr, "POST", "/sessions", json!({ "size": 2 })).await;
+        let session = created["session"].as_str().unwrap().to_string();
+        let url = format!("ws://{addr}/sessions/{session}/events");
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+        let (_, joined) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/players"),
+            json!({ "name": "alice" }),
+        )
+        .await;
+        let player = joined["player"].clone();
+        request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/query"),
+            json!({ "player": player, "input": "ab" }),
+        )
+        .await;
+        request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/give-up"),
+            json!({ "player": player }),
+        )
+        .await;
+
+        let receiving = async {
+            let mut events = vec![];
+            // the stream ends when the game is over
+            while let Some(Ok(message)) = socket.next().await {
+                if let Message::Text(text) = message {
+                    events.push(serde_json::from_str::<Value>(&text).unwrap());
+                }
+            }
+            events
+        };
+        let events = tokio::time::timeout(Duration::from_secs(5), receiving)
+            .await
+            .expect("the stream is closed");
+        let types = events
+            .iter()
+            .map(|event| event["type"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["joined", "queried", "gave_up", "revealed"]);
+        assert_eq!(events[1]["input"], "ab");
+        assert_eq!(events[0]["player"], player);
+
+        // the session and its players are forgotten
+        assert!(tokio_tungstenite::connect_async(&url).await.is_err());
+        let (status, _) = request(
+            addr,
+            "POST",
+            &format!("/sessions/{session}/guess"),
+            json!({ "player": player, "input": "a" }),
+        )
+        .await;
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn generation_failures_are_server_errors() {
+        let timeout = anyhow::Error::from(CommandError::Timeout {
+            limit: "1s".to_string(),
+        })
+        .context("timeout while generating regex");
+        assert_eq!(
+            ApiError::generation(timeout).status,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        let failure = anyhow::anyhow!("generation error");
+        assert_eq!(
+            ApiError::generation(failure).status,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -0,0 +1,291 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Settings of each guild, changed by `/config` and applied by `/start`
+
+use crate::{
+    engine::{GameMode, GuildId, Rules},
+    errors::CommandError,
+    response::{Colour, Embed},
+};
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    num::NonZeroU8,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumString};
+
+/// Language of the short notices of the BOT: rejections of commands, missing games and thanks
+/// for feedback. The games, the settings and the help are in English.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Ja,
+    En,
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Language::Ja
+    }
+}
+
+impl Language {
+    /// Picks the message in this language.
+    pub fn pick(self, ja: &'static str, en: &'static str) -> &'static str {
+        match self {
+            Language::Ja => ja,
+            Language::En => en,
+        }
+    }
+}
+
+/// Modes allowed in a guild, parsed from `all` or a mode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllowedModes(pub Vec<GameMode>);
+
+impl FromStr for AllowedModes {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(AllowedModes(GameMode::iter().collect())),
+            mode => Ok(AllowedModes(vec![mode.parse()?])),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    /// size of games started without `size`, or the default of the config if `None`
+    pub default_size: Option<NonZeroU8>,
+    /// language of the short notices of the BOT, see [Language]
+    pub language: Language,
+    /// modes `/start` accepts, whose first one is the default
+    pub allowed_modes: Vec<GameMode>,
+    /// maximum number of distinct queries in a game
+    pub query_budget: Option<usize>,
+    /// time limit of each turn in seconds
+    pub turn_time_limit: Option<u64>,
+    /// whether `/start` opens a thread for the game
+    pub thread: bool,
+    /// role allowed to use the moderation commands besides the administrators
+    pub moderator_role: Option<u64>,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        GuildSettings {
+            default_size: None,
+            language: Language::default(),
+            allowed_modes: GameMode::iter().collect(),
+            query_budget: None,
+            turn_time_limit: None,
+            thread: false,
+            moderator_role: None,
+        }
+    }
+}
+
+impl GuildSettings {
+    /// Size of a game, `size` or the default of the guild.
+    pub fn size(&self, size: Option<NonZeroU8>, default: NonZeroU8) -> NonZeroU8 {
+        size.or(self.default_size).unwrap_or(default)
+    }
+
+    /// Rules of a game, filling the omitted mode by the guild.
+    pub fn rules(
+        &self,
+        mode: Option<GameMode>,
+        turn_based: bool,
+        private_queries: bool,
+    ) -> Result<Rules, CommandError> {
+        let default = Rules::default();
+        let mode = mode
+            .or_else(|| self.allowed_modes.first().copied())
+            .unwrap_or(default.mode);
+        if !self.allowed_modes.contains(&mode) {
+            return Err(CommandError::ModeNotAllowed {
+                mode: mode.to_string(),
+                allowed: self.allowed_modes.iter().join(", "),
+            });
+        }
+        Ok(Rules {
+            mode,
+            turn_based,
+            private_queries,
+            query_budget: self.query_budget,
+            turn_time_limit: self
+                .turn_time_limit
+                .map_or(default.turn_time_limit, Duration::from_secs),
+        })
+    }
+
+    pub fn to_embed(&self, default_size: NonZeroU8) -> Embed {
+        Embed::new(Colour::DarkGreen, "SETTINGS OF THIS SERVER")
+            .field("size", self.size(None, default_size), true)
+            .field("language", self.language, true)
+            .field("modes", self.allowed_modes.iter().join(", "), true)
+            .field(
+                "query_budget",
+                self.query_budget
+                    .map_or("unlimited".to_string(), |budget| budget.to_string()),
+                true,
+            )
+            .field(
+                "time_limit",
+                format!(
+                    "{}s per turn",
+                    self.turn_time_limit
+                        .unwrap_or(Rules::default().turn_time_limit.as_secs())
+                ),
+                true,
+            )
+            .field("thread", self.thread, true)
+            .field(
+                "moderator",
+                self.moderator_role
+                    .map_or("administrators only".to_string(), |role| {
+                        format!("<@&{role}>")
+                    }),
+                true,
+            )
+    }
+}
+
+/// Settings of guilds, saved as a JSON object keyed by the guild ids
+pub struct SettingsStore {
+    path: PathBuf,
+    guilds: BTreeMap<GuildId, GuildSettings>,
+}
+
+impl SettingsStore {
+    /// Loads the settings in `path`, which may not exist yet.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let guilds = match fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str(&text).with_context(|| anyhow!("fail to parse {path:?}"))?
+            }
+            Err(why) if why.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(why) => return Err(why).with_context(|| anyhow!("fail to read {path:?}")),
+        };
+        Ok(SettingsStore { path, guilds })
+    }
+
+    /// Settings of `guild`, or the default if it has never been configured
+    pub fn get(&self, guild: GuildId) -> GuildSettings {
+        self.guilds.get(&guild).cloned().unwrap_or_default()
+    }
+
+    /// Changes the settings of `guild` by `f` and saves all the settings.
+    /// Nothing is changed if they fail to be saved.
+    pub fn update<F>(&mut self, guild: GuildId, f: F) -> anyhow::Result<GuildSettings>
+    where
+        F: FnOnce(&mut GuildSettings),
+    {
+        let mut guilds = self.guilds.clone();
+        let settings = guilds.entry(guild).or_default();
+        f(settings);
+        let settings = settings.clone();
+
+        // written to a temporary file first, not to leave a broken file
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, serde_json::to_string_pretty(&guilds)?)
+            .with_context(|| anyhow!("fail to write {temporary:?}"))?;
+        fs::rename(&temporary, &self.path)
+            .with_context(|| anyhow!("fail to write {:?}", self.path))?;
+        self.guilds = guilds;
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AllowedModes, GuildSettings, SettingsStore};
+    use crate::{
+        engine::{GameMode, GuildId},
+        errors::CommandError,
+    };
+    use std::{convert::TryInto, time::Duration};
+
+    #[test]
+    fn settings_fill_omitted_options() {
+        let settings = GuildSettings {
+            default_size: Some(5u8.try_into().unwrap()),
+            allowed_modes: "golf".parse::<AllowedModes>().unwrap().0,
+            query_budget: Some(10),
+            turn_time_limit: Some(30),
+            ..GuildSettings::default()
+        };
+        let default = 3u8.try_into().unwrap();
+        assert_eq!(settings.size(None, default).get(), 5);
+        assert_eq!(settings.size(Some(default), default).get(), 3);
+
+        let rules = settings.rules(None, true, false).unwrap();
+        assert_eq!(rules.mode, GameMode::Golf);
+        assert_eq!(rules.query_budget, Some(10));
+        assert_eq!(rules.turn_time_limit, Duration::from_secs(30));
+        assert!(matches!(
+            settings.rules(Some(GameMode::Normal), false, false),
+            Err(CommandError::ModeNotAllowed { .. })
+        ));
+        assert_eq!(
+            GuildSettings::default()
+                .rules(None, false, false)
+                .unwrap()
+                .mode,
+            GameMode::Normal
+        );
+    }
+
+    #[test]
+    fn settings_are_saved_per_guild() {
+        let path = std::env::temp_dir().join(format!("guilds-{}.json", rand::random::<u64>()));
+        let mut store = SettingsStore::open(&path).unwrap();
+        store
+            .update(GuildId(1), |settings| settings.thread = true)
+            .unwrap();
+
+        let store = SettingsStore::open(&path).unwrap();
+        assert!(store.get(GuildId(1)).thread);
+        assert_eq!(store.get(GuildId(2)), GuildSettings::default());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn settings_are_kept_if_they_fail_to_be_saved() {
+        let directory = std::env::temp_dir().join(format!("missing-{}", rand::random::<u64>()));
+        let mut store = SettingsStore::open(directory.join("guilds.json")).unwrap();
+        assert!(store
+            .update(GuildId(1), |settings| settings.thread = true)
+            .is_err());
+        assert_eq!(store.get(GuildId(1)), GuildSettings::default());
+    }
+}